@@ -1,11 +1,12 @@
 use flate2::bufread::GzDecoder;
 use fxhash::FxHasher;
 use itertools::{iproduct, Itertools};
+use pa_wrapper::io;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::hash::{Hash, Hasher};
-use std::io::{BufWriter, Cursor, Write};
+use std::io::Cursor;
 use std::path::Path;
 use std::time::Duration;
 use tar::Archive;
@@ -37,7 +38,8 @@ pub struct Experiment {
     mem_limit: Option<String>,
     datasets: Vec<DatasetConfig>,
     traces: Vec<bool>,
-    costs: Vec<CostModel>,
+    /// Explicit costs, or the names of `CostPreset`s.
+    costs: Vec<CostsOrPreset>,
     algos: Vec<AlignerParams>,
 }
 
@@ -69,6 +71,13 @@ pub struct DatasetGeneratorConfig {
 }
 
 impl Experiments {
+    /// Additionally run the given algorithms in each experiment.
+    pub fn add_algos(&mut self, algos: &[AlignerParams]) {
+        for experiment in &mut self.0 {
+            experiment.algos.extend_from_slice(algos);
+        }
+    }
+
     pub fn generate(
         self,
         data_dir: &Path,
@@ -103,7 +112,7 @@ impl Experiments {
                                 time_limit,
                                 mem_limit,
                                 dataset,
-                                costs,
+                                costs: costs.into(),
                                 traceback,
                                 algo,
                             },
@@ -132,9 +141,7 @@ impl DatasetConfig {
                 .filter_entry(|e| !is_hidden(e))
                 .filter_map(|e| {
                     let e = e.unwrap();
-                    if e.file_type().is_file()
-                        && e.path().extension().map_or(false, |ext| ext == "seq")
-                    {
+                    if e.file_type().is_file() && io::Format::from_path(e.path()).is_some() {
                         Some(e.path().to_path_buf())
                     } else {
                         None
@@ -195,11 +202,17 @@ impl DatasetConfig {
                 };
                 let path = data_dir.join(format!("manual/{hash}.seq"));
                 std::fs::create_dir_all(&path.parent().unwrap()).unwrap();
-                let mut f = BufWriter::new(std::fs::File::create(&path).unwrap());
-                for (a, b) in data {
-                    writeln!(f, ">{a}").unwrap();
-                    writeln!(f, "<{b}").unwrap();
-                }
+                let pairs = data
+                    .iter()
+                    .map(|(a, b)| {
+                        (
+                            io::Record::new(a.clone().into_bytes()),
+                            io::Record::new(b.clone().into_bytes()),
+                        )
+                    })
+                    .collect_vec();
+                io::write_pairs(&path, pairs.iter().map(|(a, b)| (a, b)))
+                    .unwrap_or_else(|err| panic!("{err}"));
                 (None, vec![Dataset::File(path)])
             }
         };
@@ -1,4 +1,4 @@
-use pa_wrapper::{AlignerParams, AlignerStats};
+use pa_wrapper::{AlignerInfo, AlignerParams, AlignerStats, Costs, CostsOrPreset, Extension};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -110,7 +110,7 @@ pub struct Job {
     /// Path to a `.seq` file.
     pub dataset: Dataset,
     /// The cost model to use.
-    pub costs: CostModel,
+    pub costs: Costs,
     /// Return the full alignment/cigar?
     pub traceback: bool,
     /// The algorithm/parameters to use.
@@ -203,6 +203,19 @@ pub struct JobOutput {
 
     /// Additional statistics such as number of expanded states and timings.
     pub stats: Option<AlignerStats>,
+
+    /// Name, version, and citation of the aligner backend.
+    #[serde(default)]
+    pub aligner_info: Option<AlignerInfo>,
+
+    /// For extension aligners, the score and end position of each extension.
+    /// `costs` is empty in this case.
+    #[serde(default)]
+    pub extensions: Option<Vec<Extension>>,
+
+    /// IDs of the pairs, when the dataset names them. Pairs without ID get their 0-based index.
+    #[serde(default)]
+    pub ids: Option<Vec<String>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -1,12 +1,10 @@
 use crate::{DatasetStats, Stats};
-use itertools::Itertools;
 use num::ToPrimitive;
 use pa_types::CigarElem;
 use pa_wrapper::wrappers::astarpa2::AstarPa2Params;
 use stats::merge_all;
 use stats::Commute;
 use std::cmp::max;
-use std::fs;
 use std::path::Path;
 
 impl<T: PartialOrd + Copy + ToPrimitive> Stats<T> {
@@ -90,17 +88,13 @@ impl Commute for DatasetStats {
 
 pub fn file_stats(file: &Path) -> DatasetStats {
     eprintln!("Generating stats for {}", file.display());
-    let data = fs::read(&file).expect("Could not read dataset file!");
+    let pairs = pa_wrapper::io::read_pairs(file)
+        .unwrap_or_else(|err| panic!("Could not read dataset file: {err}"));
     let mut aligner = AstarPa2Params::simple().make_aligner(true);
     let mut stats = merge_all(
-        data.split(|&c| c == b'\n')
-            .tuples()
-            .map(|(a, b)| {
-                (
-                    a.strip_prefix(b">").expect("Line must start with >"),
-                    b.strip_prefix(b"<").expect("Line must start with <"),
-                )
-            })
+        pairs
+            .iter()
+            .map(|(a, b)| (&a.seq[..], &b.seq[..]))
             .map(|(a, b)| {
                 let (cost, cigar) = aligner.align(&a, &b);
                 let cigar = cigar.unwrap();
@@ -0,0 +1,103 @@
+//! Measure the per-call overhead of aligners on many short pairs.
+//!
+//! For each given aligner, the same pairs are aligned twice: once reusing a single
+//! aligner for all pairs, and once building a fresh aligner for every pair.
+//! The difference is the cost of setting up the aligner, while the reused time
+//! includes any allocations and encoding the wrapper does on every call.
+//!
+//! This only uses APIs that older commits have as well, so that it can be copied into an
+//! older checkout to compare before and after a change:
+//! ```sh
+//! git worktree add ../before <commit>
+//! mkdir -p ../before/pa-bench/examples
+//! cp pa-bench/examples/call_overhead.rs ../before/pa-bench/examples/
+//! cd ../before/pa-bench
+//! cargo run --release --example call_overhead -- ksw2 parasail-striped wfa -n 100000
+//! ```
+
+use std::time::Instant;
+
+use clap::Parser;
+use pa_generate::{generate_model, ErrorModel};
+use pa_types::CostModel;
+use pa_wrapper::Aligner;
+
+#[derive(Parser, Debug)]
+struct Args {
+    /// The aligners to measure, with their default parameters.
+    ///
+    /// They must support the cost model, since unsupported parameters exit the program.
+    #[clap(value_enum, required = true)]
+    aligners: Vec<Aligner>,
+
+    /// Number of pairs.
+    #[clap(short, default_value_t = 10000)]
+    n: usize,
+
+    /// Length of each sequence.
+    #[clap(long, default_value_t = 100)]
+    len: usize,
+
+    /// Error rate between the two sequences of a pair.
+    #[clap(short, default_value_t = 0.05)]
+    e: f32,
+
+    /// Return a cigar in addition to the cost.
+    #[clap(long)]
+    trace: bool,
+
+    /// Use unit costs instead of affine costs (sub 1, open 2, extend 1).
+    #[clap(long)]
+    unit: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+    let cm = if args.unit {
+        CostModel::unit()
+    } else {
+        CostModel {
+            sub: 1,
+            open: 2,
+            extend: 1,
+        }
+    };
+    let pairs: Vec<_> = (0..args.n as u64)
+        .map(|seed| generate_model(args.len, args.e, ErrorModel::Uniform, seed))
+        .collect();
+    let max_len = pairs
+        .iter()
+        .map(|(a, b)| a.len().max(b.len()))
+        .max()
+        .unwrap_or(0);
+
+    eprintln!(
+        "{} pairs of length {}, trace: {}",
+        args.n, args.len, args.trace
+    );
+    println!("{:<14} {:>12} {:>12}", "aligner", "reused ns", "fresh ns");
+    for aligner in &args.aligners {
+        let params = aligner.default_params();
+        let (mut reused, _) = params.build_aligner(cm, args.trace, max_len);
+
+        let start = Instant::now();
+        for (a, b) in &pairs {
+            std::hint::black_box(reused.align(a, b));
+        }
+        let reused_ns = start.elapsed().as_nanos() as f64 / args.n as f64;
+
+        let start = Instant::now();
+        for (a, b) in &pairs {
+            let (mut fresh, _) = params.build_aligner(cm, args.trace, max_len);
+            std::hint::black_box(fresh.align(a, b));
+        }
+        let fresh_ns = start.elapsed().as_nanos() as f64 / args.n as f64;
+
+        println!(
+            "{:<14} {:>12.0} {:>12.0}",
+            format!("{aligner:?}"),
+            reused_ns,
+            fresh_ns
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Dataset preparation: generating, inspecting, splitting, and converting sequence files.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::builder::RangedU64ValueParser;
+use clap::ValueEnum;
+use itertools::Itertools;
+use pa_bench_types::stats::file_stats;
+use pa_bench_types::GeneratedDataset;
+use pa_generate::ErrorModel;
+use pa_types::{CostModel, Seq, Sequence};
+use pa_wrapper::{io, wrappers::astarpa2::AstarPa2Params, AlignerParams};
+use rayon::prelude::*;
+
+#[derive(clap::Args)]
+#[clap(group(clap::ArgGroup::new("amount").required(true)))]
+pub struct GenerateArgs {
+    /// Length of the first sequence of each pair.
+    #[arg(short = 'n', long, default_value_t = 1000)]
+    length: usize,
+
+    /// Error rate between the two sequences of a pair.
+    #[arg(short, long, default_value_t = 0.05)]
+    error_rate: f32,
+
+    /// The error model, e.g. Uniform, NoisyInsert, NoisyDelete, NoisyMove, NoisyDuplicate, or SymmetricRepeat.
+    #[arg(short = 'm', long, default_value = "Uniform", value_parser = parse_error_model)]
+    error_model: ErrorModel,
+
+    /// Length of the repeated pattern, for repeat error models.
+    #[arg(long)]
+    pattern_length: Option<usize>,
+
+    /// Seed for the random pairs.
+    #[arg(long, default_value_t = 31415)]
+    seed: u64,
+
+    /// Number of pairs to generate.
+    #[arg(short = 'x', long, group = "amount")]
+    count: Option<usize>,
+
+    /// Total length of the first sequences of all pairs.
+    #[arg(short, long, group = "amount")]
+    size: Option<usize>,
+
+    /// The output file. Its extension determines the format and compression.
+    ///
+    /// Defaults to the name an experiment yaml with the same settings would use, in the current directory.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn parse_error_model(model: &str) -> Result<ErrorModel, String> {
+    serde_yaml::from_str(model).map_err(|_| format!("Unknown error model {model:?}"))
+}
+
+#[derive(clap::Args)]
+pub struct StatsArgs {
+    /// Sequence files to compute statistics for.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    /// Print json instead of yaml.
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SplitBy {
+    /// By increasing divergence: the edit distance divided by the total length of the pair.
+    Divergence,
+    /// By increasing total length of the pair.
+    Length,
+    /// In input order.
+    Count,
+}
+
+#[derive(clap::Args)]
+pub struct SplitArgs {
+    /// The file to split.
+    input: PathBuf,
+
+    /// The order in which pairs are distributed over the output files.
+    #[arg(long, value_enum, default_value_t = SplitBy::Divergence)]
+    by: SplitBy,
+
+    /// Number of output files, each containing an equal share of the pairs.
+    #[arg(
+        short,
+        default_value_t = 50,
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    k: usize,
+
+    /// The output format.
+    #[arg(long, value_enum, default_value_t = io::Format::Seq)]
+    format: io::Format,
+
+    /// The output directory. Defaults to the input file without extension.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub struct ConvertArgs {
+    /// The file to convert.
+    input: PathBuf,
+
+    /// The output file. Its extension determines the format and compression.
+    output: PathBuf,
+
+    /// Format of the input, instead of detecting it from the extension or contents.
+    #[arg(long, value_enum)]
+    input_format: Option<io::Format>,
+}
+
+/// Print `err` and exit.
+fn exit_on_error<T>(result: Result<T, io::Error>) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(1);
+    })
+}
+
+/// Attach `path` to a filesystem error.
+fn fs_error(path: &Path) -> impl Fn(std::io::Error) -> io::Error + '_ {
+    move |err| io::Error {
+        path: path.to_path_buf(),
+        line: None,
+        message: err.to_string(),
+    }
+}
+
+pub fn generate(args: GenerateArgs) {
+    let dataset = GeneratedDataset {
+        prefix: PathBuf::new(),
+        seed: args.seed,
+        error_model: args.error_model,
+        error_rate: args.error_rate,
+        length: args.length,
+        total_size: args
+            .size
+            .unwrap_or_else(|| args.count.unwrap() * args.length),
+        pattern_length: args.pattern_length,
+    };
+    let output = args.output.unwrap_or_else(|| dataset.path());
+    if let Some(dir) = output.parent() {
+        exit_on_error(fs::create_dir_all(dir).map_err(fs_error(dir)));
+    }
+    // The generator writes .seq files, so other formats are converted afterwards.
+    if io::Format::from_path(&output) == Some((io::Format::Seq, io::Compression::None)) {
+        dataset.to_generator().generate_file(&output);
+    } else {
+        let tmp = output.with_extension("tmp.seq");
+        dataset.to_generator().generate_file(&tmp);
+        exit_on_error(convert_file(&tmp, &output, Some(io::Format::Seq)));
+        exit_on_error(fs::remove_file(&tmp).map_err(fs_error(&tmp)));
+    }
+    eprintln!("Generated {}", output.display());
+}
+
+pub fn stats(args: StatsArgs) {
+    for file in &args.files {
+        // Report unreadable files without panicking in `file_stats`.
+        exit_on_error(io::detect(file));
+        let stats = file_stats(file);
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&stats).unwrap());
+        } else {
+            println!("# {}", file.display());
+            print!("{}", serde_yaml::to_string(&stats).unwrap());
+        }
+    }
+}
+
+pub fn split(args: SplitArgs) {
+    let pairs = exit_on_error(io::read_pairs(&args.input));
+    let num_pairs = pairs.len();
+    eprintln!("Read {num_pairs} pairs");
+
+    fn actg_only(s: Seq) -> Sequence {
+        s.iter()
+            .copied()
+            .filter(|c| matches!(c, b'A' | b'C' | b'G' | b'T'))
+            .collect_vec()
+    }
+
+    // The key to sort by, shown in the output.
+    let mut keyed_pairs: Vec<(f32, _)> = match args.by {
+        SplitBy::Divergence => pairs
+            .into_par_iter()
+            .map(|(a, b)| {
+                let mut aligner = AlignerParams::AstarPa2(AstarPa2Params::simple())
+                    .build_aligner(CostModel::unit(), false, 0)
+                    .0;
+                let (a_seq, b_seq) = (actg_only(&a.seq), actg_only(&b.seq));
+                let cost = aligner.align(&a_seq, &b_seq).0;
+                let d = cost as f32 / (a_seq.len() + b_seq.len()).max(1) as f32;
+                (d, (a, b))
+            })
+            .collect(),
+        SplitBy::Length => pairs
+            .into_iter()
+            .map(|(a, b)| ((a.seq.len() + b.seq.len()) as f32, (a, b)))
+            .collect(),
+        SplitBy::Count => pairs
+            .into_iter()
+            .enumerate()
+            .map(|(i, pair)| (i as f32, pair))
+            .collect(),
+    };
+    keyed_pairs.sort_by(|(d1, _), (d2, _)| d1.partial_cmp(d2).unwrap());
+
+    let dir = args.output.unwrap_or_else(|| args.input.with_extension(""));
+    exit_on_error(fs::create_dir_all(&dir).map_err(fs_error(&dir)));
+    let chunk_size = num_pairs.div_ceil(args.k).max(1);
+    let digits = 1 + (args.k - 1).max(1).ilog10() as usize;
+    for (i, pairs) in keyed_pairs.chunks(chunk_size).enumerate() {
+        let path = dir.join(format!("{i:0digits$}.{}", args.format.extension()));
+        exit_on_error(io::write_pairs(
+            &path,
+            pairs.iter().map(|(_, (a, b))| (a, b)),
+        ));
+        eprintln!(
+            "{}: {:>.4} -- {:>.4}",
+            path.display(),
+            pairs[0].0,
+            pairs.last().unwrap().0
+        );
+    }
+}
+
+pub fn convert(args: ConvertArgs) {
+    exit_on_error(convert_file(&args.input, &args.output, args.input_format));
+}
+
+/// Copy all records of `input` to `output`, one at a time.
+fn convert_file(input: &Path, output: &Path, format: Option<io::Format>) -> Result<(), io::Error> {
+    let mut writer = io::create(output)?;
+    let write_error = fs_error(output);
+    for record in io::open_as(input, format)? {
+        writer.write_record(&record?).map_err(&write_error)?;
+    }
+    writer.finish().map_err(&write_error)
+}
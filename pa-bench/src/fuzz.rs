@@ -0,0 +1,298 @@
+//! Differential fuzzing of all enabled exact aligners.
+//!
+//! Random and adversarial sequence pairs are aligned by each exact aligner under
+//! each cost model it supports. Any disagreement in cost, invalid cigar, or panic
+//! is reported, minimized, and saved as a `.seq` file.
+
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use pa_generate::{generate_model, ErrorModel};
+use pa_types::*;
+use pa_wrapper::{io, Aligner, AlignerParams, Costs};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(clap::Args)]
+pub struct FuzzArgs {
+    /// Number of sequence pairs to test.
+    #[arg(short = 'n', long, default_value_t = 1000)]
+    count: usize,
+
+    /// Seed for the random pairs.
+    #[arg(long, default_value_t = 31415)]
+    seed: u64,
+
+    /// Maximum length of the generated sequences.
+    #[arg(long, default_value_t = 100)]
+    max_len: usize,
+
+    /// Comma-separated error models to generate pairs with.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "Uniform,NoisyInsert,NoisyDelete,NoisyMove,NoisyDuplicate,SymmetricRepeat"
+    )]
+    error_models: Vec<String>,
+
+    /// Yaml list of cost models to test. Defaults to unit, linear, affine, asymmetric, and match bonus costs.
+    #[arg(long, value_name = "YAML")]
+    costs: Option<String>,
+
+    /// Stop after this many failures.
+    #[arg(long, default_value_t = 10)]
+    max_failures: usize,
+
+    /// The directory to write minimized failing pairs to.
+    #[arg(long, default_value = "evals/data/fuzz")]
+    out_dir: PathBuf,
+}
+
+fn default_costs() -> Vec<Costs> {
+    serde_yaml::from_str(
+        "
+        - { sub: 1, open: 0, extend: 1 }
+        - { sub: 2, open: 0, extend: 3 }
+        - { sub: 4, open: 6, extend: 2 }
+        - { sub: 2, open: 3, extend: 1, ins_open: 1, ins_extend: 2 }
+        - { sub: 4, open: 4, extend: 2, match_bonus: 2 }
+        - { sub: 4, open: 6, extend: 1, match_bonus: 1 }
+        ",
+    )
+    .unwrap()
+}
+
+/// A random or adversarial sequence pair.
+fn generate_pair(
+    rng: &mut StdRng,
+    args: &FuzzArgs,
+    error_models: &[ErrorModel],
+) -> (Vec<u8>, Vec<u8>) {
+    let random_seq = |rng: &mut StdRng, n: usize| -> Vec<u8> {
+        (0..n).map(|_| b"ACGT"[rng.gen_range(0..4)]).collect()
+    };
+    let max_len = args.max_len.max(1);
+    let n = rng.gen_range(1..=max_len);
+    // Half of the pairs are adversarial edge cases, the other half use an error model.
+    match rng.gen_range(0..16) {
+        0 => (vec![], random_seq(rng, n)),
+        1 => (random_seq(rng, n), vec![]),
+        2 => (vec![], vec![]),
+        3 => {
+            let a = random_seq(rng, n);
+            (a.clone(), a)
+        }
+        4 => (vec![b'A'; n], vec![b'A'; rng.gen_range(0..=max_len)]),
+        5 => {
+            let m = rng.gen_range(1..=max_len);
+            (random_seq(rng, n), random_seq(rng, m))
+        }
+        6 => {
+            // Tandem repeats with a different number of copies.
+            let len = rng.gen_range(1..=5);
+            let unit = random_seq(rng, len);
+            let copies = |c: usize| unit.repeat(c);
+            let c = (n / unit.len()).max(1);
+            (copies(c), copies(rng.gen_range(1..=c + 2)))
+        }
+        7 => {
+            // A single long gap.
+            let a = random_seq(rng, n);
+            let start = rng.gen_range(0..=n);
+            let end = rng.gen_range(start..=n);
+            let b = [&a[..start], &a[end..]].concat();
+            if rng.gen() {
+                (a, b)
+            } else {
+                (b, a)
+            }
+        }
+        _ => {
+            const ERROR_RATES: [f32; 6] = [0.0, 0.01, 0.05, 0.1, 0.2, 0.5];
+            let e = ERROR_RATES[rng.gen_range(0..ERROR_RATES.len())];
+            let error_model = error_models[rng.gen_range(0..error_models.len())];
+            generate_model(n, e, error_model, rng.gen())
+        }
+    }
+}
+
+/// Align `a` and `b` with all `aligners` under `costs`, and describe the first problem found, if any.
+fn check(aligners: &[AlignerParams], costs: Costs, a: Seq, b: Seq) -> Option<String> {
+    let max_len = a.len().max(b.len());
+    // The first successful (aligner, cost) pair.
+    let mut reference: Option<(&AlignerParams, Cost)> = None;
+    for params in aligners {
+        for trace in [false, true] {
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                let Ok((mut aligner, _)) = params.try_build_aligner(costs, trace, max_len) else {
+                    return None;
+                };
+                Some(aligner.align(a, b))
+            }));
+            let (cost, cigar, _stats) = match result {
+                Ok(Some(result)) => result,
+                // Unsupported parameters.
+                Ok(None) => continue,
+                Err(payload) => {
+                    let msg = payload
+                        .downcast_ref::<String>()
+                        .cloned()
+                        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                        .unwrap_or_default();
+                    return Some(format!("{params:?} (trace: {trace}) panicked: {msg}"));
+                }
+            };
+            if let Some(cigar) = cigar {
+                match costs.verify(&cigar, a, b) {
+                    Err(err) => {
+                        return Some(format!(
+                            "{params:?} returned an invalid cigar {}: {err}",
+                            cigar.to_string()
+                        ))
+                    }
+                    Ok(cigar_cost) if cigar_cost != cost => {
+                        return Some(format!(
+                        "{params:?} returned cost {cost} but its cigar {} has cost {cigar_cost}",
+                        cigar.to_string()
+                    ))
+                    }
+                    Ok(_) => {}
+                }
+            }
+            match reference {
+                None => reference = Some((params, cost)),
+                Some((ref_params, ref_cost)) if ref_cost != cost => {
+                    return Some(format!(
+                        "{params:?} (trace: {trace}) returned cost {cost} but {ref_params:?} returned cost {ref_cost}"
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    None
+}
+
+/// Greedily remove chunks of `a` and `b` while the pair still fails.
+fn minimize(
+    aligners: &[AlignerParams],
+    costs: Costs,
+    mut a: Vec<u8>,
+    mut b: Vec<u8>,
+) -> (Vec<u8>, Vec<u8>) {
+    let fails = |a: &[u8], b: &[u8]| check(aligners, costs, a, b).is_some();
+    let mut chunk = a.len().max(b.len()).next_power_of_two();
+    while chunk > 0 {
+        let mut progress = false;
+        for side in [0, 1] {
+            let mut start = 0;
+            loop {
+                let s = if side == 0 { &a } else { &b };
+                if start >= s.len() {
+                    break;
+                }
+                let end = (start + chunk).min(s.len());
+                let shorter = [&s[..start], &s[end..]].concat();
+                let still_fails = if side == 0 {
+                    fails(&shorter, &b)
+                } else {
+                    fails(&a, &shorter)
+                };
+                if still_fails {
+                    if side == 0 {
+                        a = shorter;
+                    } else {
+                        b = shorter;
+                    }
+                    progress = true;
+                } else {
+                    start = end;
+                }
+            }
+        }
+        if !progress {
+            chunk /= 2;
+        }
+    }
+    (a, b)
+}
+
+pub fn fuzz(args: FuzzArgs) {
+    let error_models: Vec<ErrorModel> = args
+        .error_models
+        .iter()
+        .map(|m| serde_yaml::from_str(m).unwrap_or_else(|_| panic!("Unknown error model {m:?}")))
+        .collect();
+    let costs: Vec<Costs> = match &args.costs {
+        Some(costs) => serde_yaml::from_str(costs).expect("Failed to parse cost models as yaml"),
+        None => default_costs(),
+    };
+    let aligners: Vec<AlignerParams> = Aligner::value_variants()
+        .iter()
+        .map(|aligner| aligner.default_params())
+        .filter(|params| params.is_exact())
+        .collect();
+    eprintln!(
+        "Fuzzing {} exact aligners: {:?}",
+        aligners.len(),
+        aligners.iter().map(|p| p.info().name).collect::<Vec<_>>()
+    );
+
+    // Panics are caught and reported as failures.
+    std::panic::set_hook(Box::new(|_| {}));
+
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let mut failures = 0;
+    for i in 0..args.count {
+        let (a, b) = generate_pair(&mut rng, &args, &error_models);
+        for &costs in &costs {
+            if check(&aligners, costs, &a, &b).is_none() {
+                continue;
+            }
+            let (a, b) = minimize(&aligners, costs, a.clone(), b.clone());
+            let failure = check(&aligners, costs, &a, &b).unwrap();
+
+            fs::create_dir_all(&args.out_dir).unwrap();
+            let path = args.out_dir.join(format!(
+                "fuzz-{}-{i}-{}.seq",
+                args.seed,
+                serde_json::to_string(&costs)
+                    .unwrap()
+                    .replace(|c: char| !c.is_ascii_alphanumeric(), "")
+            ));
+            io::write_pairs(
+                &path,
+                [(&io::Record::new(a.clone()), &io::Record::new(b.clone()))],
+            )
+            .unwrap_or_else(|err| {
+                // The panic hook is silenced, so report the error directly.
+                eprintln!("{err}");
+                std::process::exit(1);
+            });
+
+            eprintln!(
+                "\nFAILURE for pair {i} with costs {costs:?}:\n{failure}\nA: {}\nB: {}\nSaved to {}",
+                String::from_utf8_lossy(&a),
+                String::from_utf8_lossy(&b),
+                path.display()
+            );
+            failures += 1;
+            if failures >= args.max_failures {
+                let _ = std::panic::take_hook();
+                eprintln!("Stopping after {failures} failures.");
+                std::process::exit(1);
+            }
+        }
+        eprint!(
+            "\rTested {:>6} / {} pairs, {failures} failures",
+            i + 1,
+            args.count
+        );
+    }
+    let _ = std::panic::take_hook();
+    eprintln!();
+    if failures > 0 {
+        std::process::exit(1);
+    }
+}
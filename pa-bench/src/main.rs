@@ -7,7 +7,10 @@
 //! - cache shrink
 
 mod bench;
+mod dataset;
+mod fuzz;
 mod runner;
+mod tune;
 
 use chrono::Timelike;
 use clap::{Parser, Subcommand};
@@ -40,28 +43,23 @@ enum SubCommand {
     Bench(BenchArgs),
     /// Run a single Job given on stdin, and print results to stdout.
     Run(runner::Args),
+    /// Compare all exact aligners on random inputs, and save failing inputs.
+    Fuzz(fuzz::FuzzArgs),
+    /// Find the fastest parameters of an inexact aligner that meet an accuracy target.
+    Tune(tune::TuneArgs),
+    /// Generate a dataset of random sequence pairs with `pa-generate`.
+    Generate(dataset::GenerateArgs),
+    /// Print the length, divergence, and gap statistics of sequence files.
+    Stats(dataset::StatsArgs),
+    /// Split a sequence file into files by divergence, length, or count.
+    Split(dataset::SplitArgs),
+    /// Convert a sequence file between .seq, .txt, FASTA, and FASTQ, and compressions.
+    Convert(dataset::ConvertArgs),
 }
 
+/// Options for running jobs, shared by `bench` and `tune`.
 #[derive(clap::Args)]
-struct BenchArgs {
-    /// Path to an experiment yaml file.
-    #[arg(num_args = 1..)]
-    experiments: Vec<PathBuf>,
-
-    /// Path to the output json file. By default mirrors the `experiments` dir in `results`.
-    ///
-    /// Only works if only a single experiment is given.
-    #[arg(short = 'o', long)]
-    output: Option<PathBuf>,
-
-    /// Shared cache of JobResults. Default: <experiment>.cache.json.
-    #[arg(long)]
-    cache: Option<PathBuf>,
-
-    /// Completely disable using a cache.
-    #[arg(long)]
-    no_cache: bool,
-
+struct JobArgs {
     /// Number of parallel jobs to use.
     ///
     /// Jobs are pinned to separate cores.
@@ -89,6 +87,46 @@ struct BenchArgs {
     #[clap(help_heading = "Limits")]
     no_pin: bool,
 
+    /// Print jobs started and finished.
+    #[arg(short, long)]
+    #[clap(help_heading = "Output")]
+    verbose: bool,
+
+    /// Show stderr of runner process.
+    #[arg(long)]
+    #[clap(help_heading = "Output")]
+    stderr: bool,
+
+    /// The directory to store generated/downloaded data.
+    #[arg(long, default_value = "evals/data")]
+    #[clap(help_heading = "Custom paths")]
+    #[clap(hide_short_help = true)]
+    data_dir: PathBuf,
+}
+
+#[derive(clap::Args)]
+struct BenchArgs {
+    /// Path to an experiment yaml file.
+    #[arg(num_args = 1..)]
+    experiments: Vec<PathBuf>,
+
+    /// Path to the output json file. By default mirrors the `experiments` dir in `results`.
+    ///
+    /// Only works if only a single experiment is given.
+    #[arg(short = 'o', long)]
+    output: Option<PathBuf>,
+
+    /// Shared cache of JobResults. Default: <experiment>.cache.json.
+    #[arg(long)]
+    cache: Option<PathBuf>,
+
+    /// Completely disable using a cache.
+    #[arg(long)]
+    no_cache: bool,
+
+    #[clap(flatten)]
+    jobs: JobArgs,
+
     /// Ignore job cache, i.e. rerun jobs already present in the results file.
     ///
     /// By default, already-present jobs are reused.
@@ -120,22 +158,6 @@ struct BenchArgs {
     #[arg(long)]
     release: bool,
 
-    /// Print jobs started and finished.
-    #[arg(short, long)]
-    #[clap(help_heading = "Output")]
-    verbose: bool,
-
-    /// Show stderr of runner process.
-    #[arg(long)]
-    #[clap(help_heading = "Output")]
-    stderr: bool,
-
-    /// The directory to store generated/downloaded data.
-    #[arg(long, default_value = "evals/data")]
-    #[clap(help_heading = "Custom paths")]
-    #[clap(hide_short_help = true)]
-    data_dir: PathBuf,
-
     /// Path to the logs directory.
     ///
     /// Results of all runs are additionally stored here as a backup.
@@ -152,14 +174,20 @@ fn main() {
     match args.command {
         SubCommand::Bench(args) => bench(args),
         SubCommand::Run(args) => runner::main(args),
+        SubCommand::Tune(args) => tune::tune(args),
+        SubCommand::Fuzz(args) => fuzz::fuzz(args),
+        SubCommand::Generate(args) => dataset::generate(args),
+        SubCommand::Stats(args) => dataset::stats(args),
+        SubCommand::Split(args) => dataset::split(args),
+        SubCommand::Convert(args) => dataset::convert(args),
     }
 }
 
 fn bench(mut args: BenchArgs) {
     // Handle `--release` flag.
     if args.release {
-        args.nice.get_or_insert(-20);
-        args.num_jobs = 1;
+        args.jobs.nice.get_or_insert(-20);
+        args.jobs.num_jobs = 1;
     }
 
     if args.output.is_some() && args.experiments.len() != 1 {
@@ -167,6 +195,19 @@ fn bench(mut args: BenchArgs) {
         std::process::exit(1);
     }
 
+    let runner_cores = setup_orchestrator(args.jobs.num_jobs);
+
+    for i in 0..args.experiments.len() {
+        run_experiment(&args, i, &runner_cores);
+        if !*RUNNING.lock().unwrap() {
+            break;
+        }
+    }
+}
+
+/// Set a Ctrl-C handler that stops running jobs, pin the orchestrator to a
+/// core, and return the (up to) `num_jobs` cores for runners.
+fn setup_orchestrator(num_jobs: usize) -> Vec<usize> {
     {
         ctrlc::set_handler(move || {
             eprintln!("Pressed Ctrl-C. Stopping running jobs.");
@@ -182,21 +223,14 @@ fn bench(mut args: BenchArgs) {
         // physical core, in case hyperthreading is enabled.
         // TODO(ragnar): Is it better to spread the load over non-adjacent
         // physical cores? Unclear to me.
-        .take(args.num_jobs + 1);
+        .take(num_jobs + 1);
 
     // Reserve one core for the orchestrator.
     let orchestrator_core = cores.next().unwrap();
     core_affinity::set_for_current(orchestrator_core);
 
     // Remaining (up to) #processes cores are for runners.
-    let runner_cores = cores.map(|c| c.id).collect();
-
-    for i in 0..args.experiments.len() {
-        run_experiment(&args, i, &runner_cores);
-        if !*RUNNING.lock().unwrap() {
-            break;
-        }
-    }
+    cores.map(|c| c.id).collect()
 }
 
 fn run_experiment(args: &BenchArgs, experiment_idx: usize, runner_cores: &Vec<usize>) {
@@ -233,10 +267,10 @@ fn run_experiment(args: &BenchArgs, experiment_idx: usize, runner_cores: &Vec<us
     };
 
     let mut jobs = experiments.generate(
-        &args.data_dir,
+        &args.jobs.data_dir,
         args.regenerate,
-        args.time_limit,
-        args.mem_limit,
+        args.jobs.time_limit,
+        args.jobs.mem_limit,
     );
     eprintln!("Generated {} jobs.", jobs.len());
 
@@ -363,10 +397,10 @@ fn run_experiment(args: &BenchArgs, experiment_idx: usize, runner_cores: &Vec<us
             Some(&existing_jobs_in_experiment)
         },
         runner_cores,
-        args.nice,
-        args.stderr,
-        args.no_pin,
-        args.verbose,
+        args.jobs.nice,
+        args.jobs.stderr,
+        args.jobs.no_pin,
+        args.jobs.verbose,
     );
 
     let verified_ok = verify_costs(
@@ -513,9 +547,13 @@ fn verify_costs(
                         .zip(&reference_output.costs)
                         .position(|(&a, &b)| a != b)
                         .unwrap();
+                    let id = output
+                        .ids
+                        .as_ref()
+                        .map_or(String::new(), |ids| format!(" (id {})", ids[idx]));
                     // For exact jobs, simply check they give the same result.
                     eprintln!(
-                        "\nTwo exact algorithms returned different costs!\nJob 1: {:?}\nJob 2: {:?}\nSequence idx {idx}\nCost 1: {:?}\nCost 2: {:?}\n",
+                        "\nTwo exact algorithms returned different costs!\nJob 1: {:?}\nJob 2: {:?}\nSequence idx {idx}{id}\nCost 1: {:?}\nCost 2: {:?}\n",
                         result.job,
                         reference_result.job,
                         output.costs[idx],
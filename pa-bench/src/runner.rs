@@ -1,9 +1,9 @@
 use crate::bench::*;
 
-use itertools::{izip, Itertools};
+use itertools::izip;
 use pa_bench_types::*;
 use pa_types::Seq;
-use pa_wrapper::{merge_stats, AlignerStats};
+use pa_wrapper::{merge_stats, AlignerStats, AnyAligner};
 
 use std::{
     cmp::max,
@@ -42,28 +42,38 @@ pub struct Args {
     verbose: bool,
 }
 
+/// Read the pairs in `path` into `file_data`, and return them with their IDs, if any.
 fn read_path<'a>(
-    path: &std::path::PathBuf,
+    path: &Path,
     file_data: &'a mut Vec<u8>,
-) -> Vec<(&'a [u8], &'a [u8])> {
-    assert_eq!(
-        path.extension()
-            .expect("Dataset does not have a file extension"),
-        "seq",
-        "Job dataset {} does not have extension .seq.",
-        path.display()
-    );
-    *file_data = fs::read(&path).expect("Could not read dataset file");
-    file_data
-        .split(|&c| c == '\n' as u8)
-        .tuples()
-        .map(|(a, b)| {
-            (
-                a.strip_prefix(b">").expect("Odd lines must start with >"),
-                b.strip_prefix(b"<").expect("Even lines must start with <"),
-            )
-        })
-        .collect()
+) -> (Vec<(&'a [u8], &'a [u8])>, Option<Vec<String>>) {
+    let pairs = pa_wrapper::io::read_pairs(path)
+        .unwrap_or_else(|err| panic!("Could not read dataset file: {err}"));
+    let ids = pairs
+        .iter()
+        .any(|(a, b)| pa_wrapper::io::pair_id(a, b).is_some())
+        .then(|| {
+            pairs
+                .iter()
+                .enumerate()
+                .map(|(i, (a, b))| pa_wrapper::io::pair_id(a, b).map_or(i.to_string(), Into::into))
+                .collect()
+        });
+    // Copy all sequences into one allocation, and slice them afterwards.
+    let mut ranges = Vec::with_capacity(pairs.len());
+    for (a, b) in &pairs {
+        let start = file_data.len();
+        file_data.extend_from_slice(&a.seq);
+        let mid = file_data.len();
+        file_data.extend_from_slice(&b.seq);
+        ranges.push((start..mid, mid..file_data.len()));
+    }
+    let file_data: &'a [u8] = file_data;
+    let sequence_pairs = ranges
+        .into_iter()
+        .map(|(a, b)| (&file_data[a], &file_data[b]))
+        .collect();
+    (sequence_pairs, ids)
 }
 
 pub fn main(args: Args) {
@@ -105,15 +115,16 @@ fn run_job(args: &Args, job: Job) -> JobOutput {
     // This way all data is stored within one big allocation instead of being spread over many Vecs.
     let file_data = &mut vec![];
     let input_data;
-    let sequence_pairs: Vec<(Seq, Seq)> = match &job.dataset {
+    let (sequence_pairs, ids): (Vec<(Seq, Seq)>, _) = match &job.dataset {
         Dataset::Generated(generated_dataset) => read_path(&generated_dataset.path(), file_data),
         Dataset::File(path) => read_path(path, file_data),
         Dataset::Data(data) => {
             input_data = data.clone();
-            input_data
+            let sequence_pairs = input_data
                 .iter()
                 .map(|(a, b)| (a.as_bytes(), b.as_bytes()))
-                .collect()
+                .collect();
+            (sequence_pairs, None)
         }
     };
 
@@ -123,17 +134,38 @@ fn run_job(args: &Args, job: Job) -> JobOutput {
         .max()
         .unwrap_or(0);
 
+    let is_extension = job.algo.is_extension();
     let mut costs = Vec::with_capacity(sequence_pairs.len());
+    let mut extensions = Vec::with_capacity(sequence_pairs.len());
     let mut cigars = Vec::with_capacity(sequence_pairs.len());
     let mut total_stats = AlignerStats::default();
     let mut is_exact = false;
 
     let measured = measure(|| {
-        let mut aligner;
-        (aligner, is_exact) = job.algo.build_aligner(job.costs, job.traceback, max_len);
+        let mut aligner = if is_extension {
+            AnyAligner::Extension(job.algo.build_extension_aligner(
+                job.costs,
+                job.traceback,
+                max_len,
+            ))
+        } else {
+            let aligner;
+            (aligner, is_exact) = job.algo.build_aligner(job.costs, job.traceback, max_len);
+            AnyAligner::Global(aligner)
+        };
         sequence_pairs.iter().for_each(|(a, b)| {
-            let (cost, cigar, stats) = aligner.align(a, b);
-            costs.push(cost);
+            let (cigar, stats) = match &mut aligner {
+                AnyAligner::Extension(aligner) => {
+                    let (extension, cigar, stats) = aligner.align_extension(a, b);
+                    extensions.push(extension);
+                    (cigar, stats)
+                }
+                AnyAligner::Global(aligner) => {
+                    let (cost, cigar, stats) = aligner.align(a, b);
+                    costs.push(cost);
+                    (cigar, stats)
+                }
+            };
             merge_stats(&mut total_stats, stats);
             if job.traceback {
                 cigars.push(cigar);
@@ -143,10 +175,27 @@ fn run_job(args: &Args, job: Job) -> JobOutput {
     });
 
     // Verify the cigar strings, but do not return them as they are not used for further analysis and take a lot of space.
-    if job.traceback {
+    if job.traceback && is_extension {
+        for ((a, b), extension, cigar) in izip!(sequence_pairs, &extensions, cigars) {
+            if let Some(cigar) = cigar {
+                let (a, b) = (&a[..extension.a_end], &b[..extension.b_end]);
+                let cigar_cost = job.costs.verify(&cigar, a, b).unwrap();
+                let score = job.costs.score(cigar_cost, a.len(), b.len());
+                assert_eq!(
+                    score,
+                    extension.score,
+                    "\nEXTENSION SCORE IS NOT CORRECT\njob: {job:?}\nA: {}\nB: {}\nCigar: {:?}\nreturned score: {}\nactual score: {score}\n",
+                    String::from_utf8(a.to_vec()).unwrap(),
+                    String::from_utf8(b.to_vec()).unwrap(),
+                    cigar.to_string(),
+                    extension.score,
+                );
+            }
+        }
+    } else if job.traceback {
         for ((a, b), &cost, cigar) in izip!(sequence_pairs, &costs, cigars) {
             if let Some(cigar) = cigar {
-                let cigar_cost = cigar.verify(&job.costs, a, b).unwrap();
+                let cigar_cost = job.costs.verify(&cigar, a, b).unwrap();
                 if is_exact {
                     assert_eq!(
                         cigar_cost,
@@ -169,6 +218,9 @@ fn run_job(args: &Args, job: Job) -> JobOutput {
         p_correct: None,
         measured,
         stats: Some(total_stats),
+        aligner_info: Some(job.algo.info()),
+        extensions: is_extension.then_some(extensions),
+        ids,
     };
     output
 }
@@ -0,0 +1,258 @@
+//! Find the fastest parameters of an inexact aligner that meet an accuracy target.
+//!
+//! All candidate parameters are run on all inputs of an experiment using the
+//! usual runner, and their costs are compared to those of an exact aligner
+//! using `verify_costs`. Exact aligners only serve as ground truth.
+
+use std::fs;
+use std::path::PathBuf;
+
+use itertools::{iproduct, Itertools};
+use pa_bench_types::*;
+use pa_wrapper::wrappers::block_aligner::{BlockAlignerParams, BlockAlignerSize};
+use pa_wrapper::wrappers::edlib::EdlibParams;
+use pa_wrapper::wrappers::ksw2::Ksw2Params;
+use pa_wrapper::wrappers::triple_accel::TripleAccelParams;
+use pa_wrapper::wrappers::wfa::{Heuristic, WfaParams};
+use pa_wrapper::Aligner;
+
+use crate::*;
+
+#[derive(clap::Args)]
+#[clap(group(clap::ArgGroup::new("target").required(true)))]
+pub struct TuneArgs {
+    /// Path to an experiment yaml file.
+    ///
+    /// Its `algos` are the candidate parameters, and its datasets, costs, and traces
+    /// are the inputs they must meet the target on.
+    experiment: PathBuf,
+
+    /// Additionally search the built-in parameter space of this aligner:
+    /// block sizes for BlockAligner, fixed bands for Ksw2, `k` for Edlib and TripleAccel,
+    /// and the adaptive and X-drop heuristics for Wfa.
+    #[arg(long, value_name = "ALIGNER")]
+    search: Option<Aligner>,
+
+    /// Exact aligner to compute the ground truth with, when `algos` contains none.
+    #[arg(long, value_name = "ALIGNER")]
+    reference: Option<Aligner>,
+
+    /// Minimal fraction of correct costs on each input.
+    #[arg(long, group = "target")]
+    p_correct: Option<f32>,
+
+    /// Maximal relative error of the total cost on each input: `(sum(cost) - sum(exact)) / sum(exact)`.
+    #[arg(long, group = "target")]
+    max_relative_error: Option<f32>,
+
+    #[clap(flatten)]
+    jobs: JobArgs,
+}
+
+/// The built-in parameter space of an aligner, from fast to accurate.
+fn search_space(aligner: Aligner) -> Vec<AlignerParams> {
+    const BANDS: [usize; 8] = [10, 20, 50, 100, 200, 500, 1000, 2000];
+    match aligner {
+        Aligner::BlockAligner => iproduct!([8, 16, 32, 64, 128], [256, 1024, 4096, 16384])
+            .map(|(min, max)| {
+                AlignerParams::BlockAligner(BlockAlignerParams {
+                    size: BlockAlignerSize::Size(min, max),
+                    x_drop: None,
+                })
+            })
+            .collect(),
+        Aligner::Ksw2 => BANDS
+            .into_iter()
+            .map(|band| {
+                AlignerParams::Ksw2(Ksw2Params {
+                    band_doubling: false,
+                    band: Some(band),
+                    ..Ksw2Params::default()
+                })
+            })
+            .collect(),
+        Aligner::Edlib => BANDS
+            .into_iter()
+            .map(|k| AlignerParams::Edlib(EdlibParams { k: Some(k) }))
+            .collect(),
+        Aligner::TripleAccel => BANDS
+            .into_iter()
+            .map(|k| AlignerParams::TripleAccel(TripleAccelParams { k: Some(k as u32) }))
+            .collect(),
+        Aligner::Wfa => {
+            let adaptive =
+                iproduct!([10, 50, 100], [50, 100, 200, 500]).map(|(min_length, max_distance)| {
+                    Heuristic::WFadaptive(min_length, max_distance, 10)
+                });
+            let x_drop = [20, 50, 100, 200, 500, 1000]
+                .into_iter()
+                .map(|x_drop| Heuristic::XDrop(x_drop, 10));
+            adaptive
+                .chain(x_drop)
+                .map(|heuristic| {
+                    AlignerParams::Wfa(WfaParams {
+                        heuristic,
+                        ..WfaParams::default()
+                    })
+                })
+                .collect()
+        }
+        aligner => {
+            eprintln!(
+                "No built-in search space for {aligner:?}. List candidate parameters in `algos` instead."
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The accuracy of a candidate on a single input.
+struct Accuracy {
+    p_correct: f32,
+    relative_error: f32,
+}
+
+fn accuracy(output: &JobOutput) -> Option<Accuracy> {
+    let exact_costs = output.exact_costs.as_ref()?;
+    // Failed alignments (e.g. out of band) return `Cost::MAX`, so sum in 64 bits.
+    let total: i64 = output.costs.iter().map(|&c| c as i64).sum();
+    let exact_total: i64 = exact_costs.iter().map(|&c| c as i64).sum();
+    Some(Accuracy {
+        p_correct: output.p_correct?,
+        relative_error: if exact_total == 0 {
+            if total == 0 {
+                0.
+            } else {
+                f32::INFINITY
+            }
+        } else {
+            (total - exact_total) as f32 / exact_total as f32
+        },
+    })
+}
+
+pub fn tune(args: TuneArgs) {
+    let runner_cores = setup_orchestrator(args.jobs.num_jobs);
+
+    let experiment_yaml =
+        fs::read_to_string(&args.experiment).expect("Failed to read jobs generator:");
+    let mut experiments: Experiments =
+        serde_yaml::from_str(&experiment_yaml).expect("Failed to parse jobs generator yaml:");
+
+    // Add the reference and built-in candidates to each experiment.
+    let mut extra_algos = vec![];
+    if let Some(reference) = args.reference {
+        let params = reference.default_params();
+        if !params.is_exact() {
+            eprintln!("Reference aligner {reference:?} is not exact.");
+            std::process::exit(1);
+        }
+        extra_algos.push(params);
+    }
+    if let Some(aligner) = args.search {
+        extra_algos.extend(search_space(aligner));
+    }
+    experiments.add_algos(&extra_algos);
+
+    let jobs = experiments.generate(
+        &args.jobs.data_dir,
+        false,
+        args.jobs.time_limit,
+        args.jobs.mem_limit,
+    );
+    if !jobs.iter().any(|(job, _)| job.algo.is_exact()) {
+        eprintln!("No exact aligner to compare against. Add one to `algos` or use `--reference`.");
+        std::process::exit(1);
+    }
+    eprintln!("Running {} jobs...", jobs.len());
+
+    let current_exe = match std::env::current_exe() {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Could not determine path to executable needed for self-invocation:\n{e}");
+            std::process::exit(1);
+        }
+    };
+    let mut job_results = run_with_threads(
+        &current_exe,
+        jobs,
+        None,
+        &runner_cores,
+        args.jobs.nice,
+        args.jobs.stderr,
+        args.jobs.no_pin,
+        args.jobs.verbose,
+    );
+    if !verify_costs(&mut job_results, &vec![], &vec![]) {
+        eprintln!("\nA JOB FOR AN EXACT ALIGNER FAILED OUTPUT COST & CIGAR VERIFICATION!\nSEE LOGS ABOVE.\nPLEASE CHECK AND REPORT AN ISSUE.");
+        std::process::exit(1);
+    }
+
+    // Group results by candidate, and keep those meeting the target on all inputs.
+    let mut best: Option<(f32, &AlignerParams)> = None;
+    eprintln!(
+        "{:>10} {:>10} {:>10}  params",
+        "runtime", "p_correct", "rel_error"
+    );
+    for (_, results) in &job_results
+        .iter()
+        .sorted_by_key(|r| serde_json::to_string(&r.job.algo).unwrap())
+        .group_by(|r| serde_json::to_string(&r.job.algo).unwrap())
+    {
+        let results = results.collect_vec();
+        let algo = &results[0].job.algo;
+        // Exact aligners only provide the ground truth.
+        if algo.is_exact() {
+            continue;
+        }
+        let mut runtime = 0.;
+        let mut min_p_correct = 1f32;
+        let mut max_relative_error = 0f32;
+        let mut ok = true;
+        for result in &results {
+            match result
+                .output
+                .as_ref()
+                .ok()
+                .and_then(|o| Some((o, accuracy(o)?)))
+            {
+                Some((output, accuracy)) => {
+                    runtime += output.measured.runtime;
+                    min_p_correct = min_p_correct.min(accuracy.p_correct);
+                    max_relative_error = max_relative_error.max(accuracy.relative_error);
+                }
+                // Failed or unverified jobs disqualify the candidate.
+                None => ok = false,
+            }
+        }
+        let meets_target = ok
+            && args.p_correct.map_or(true, |p| min_p_correct >= p)
+            && args
+                .max_relative_error
+                .map_or(true, |e| max_relative_error <= e);
+        eprintln!(
+            "{:>10.3} {:>10.4} {:>10.4}  {}{}",
+            runtime,
+            min_p_correct,
+            max_relative_error,
+            serde_json::to_string(algo).unwrap(),
+            if !ok {
+                " (failed)"
+            } else if meets_target {
+                ""
+            } else {
+                " (below target)"
+            }
+        );
+        if meets_target && best.map_or(true, |(t, _)| runtime < t) {
+            best = Some((runtime, algo));
+        }
+    }
+
+    let Some((runtime, algo)) = best else {
+        eprintln!("No candidate meets the target.");
+        std::process::exit(1);
+    };
+    eprintln!("Fastest candidate meeting the target takes {runtime:.3}s:");
+    println!("{}", serde_yaml::to_string(algo).unwrap());
+}
@@ -0,0 +1,249 @@
+//! The sequence pairs to align: consecutive records of a single file, or
+//! queries and targets from two files.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+use clap::ValueEnum;
+use itertools::{EitherOrBoth, Itertools};
+use pa_wrapper::io::{self, Record};
+
+/// How to pair the records of the query and target files.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Pairing {
+    /// The i'th query with the i'th target.
+    Index,
+    /// Each query with the target of the same name.
+    Name,
+    /// Every query with every target.
+    AllVsAll,
+    /// The single query with every target.
+    OneVsAll,
+}
+
+/// A pair of sequences to align.
+pub struct Pair {
+    /// The ID columns that csv output lines start with, if any.
+    pub key: Option<String>,
+    /// The names of the query `a` and target `b`: their record names or 0-based indices.
+    pub names: (String, String),
+    pub a: Record,
+    pub b: Record,
+}
+
+pub type Pairs = Box<dyn Iterator<Item = Result<Pair, io::Error>>>;
+
+fn error(path: &Path, message: impl Into<String>) -> io::Error {
+    io::Error {
+        path: path.to_path_buf(),
+        line: None,
+        message: message.into(),
+    }
+}
+
+/// Consecutive pairs of records in a single file.
+///
+/// With `with_ids`, pairs are keyed by their ID, or by their index when they have none.
+pub fn file_pairs(
+    path: &Path,
+    format: Option<io::Format>,
+    with_ids: bool,
+) -> Result<Pairs, io::Error> {
+    Ok(Box::new(
+        io::open_as(path, format)?
+            .pairs()
+            .enumerate()
+            .map(move |(idx, pair)| {
+                let (a, b) = pair?;
+                let key = with_ids.then(|| io::pair_id(&a, &b).map_or(idx.to_string(), Into::into));
+                Ok(Pair {
+                    key,
+                    names: (name(idx, &a), name(idx, &b)),
+                    a,
+                    b,
+                })
+            }),
+    ))
+}
+
+/// The name of the `idx`'th record of a query or target file, defaulting to its index.
+pub fn name(idx: usize, record: &Record) -> String {
+    record.id.clone().unwrap_or_else(|| idx.to_string())
+}
+
+/// The names and lengths of the targets, for SAM `@SQ` lines: the records of `targets`, or
+/// else the second record of each pair of `input`, named by its pair index when it has no name.
+///
+/// Targets with the same name and length are listed once. Different targets with the same
+/// name are an error, since SAM lines refer to their target by name.
+pub fn target_lengths(
+    input: &Path,
+    targets: Option<&Path>,
+    format: Option<io::Format>,
+) -> Result<Vec<(String, usize)>, io::Error> {
+    let (path, named_targets): (_, Box<dyn Iterator<Item = _>>) = match targets {
+        Some(targets) => (
+            targets,
+            Box::new(
+                io::open_as(targets, format)?
+                    .enumerate()
+                    .map(|(idx, t)| t.map(|t| (name(idx, &t), t.seq.len()))),
+            ),
+        ),
+        None => (
+            input,
+            Box::new(
+                file_pairs(input, format, false)?.map(|p| p.map(|p| (p.names.1, p.b.seq.len()))),
+            ),
+        ),
+    };
+    let mut lengths = HashMap::new();
+    let mut targets = vec![];
+    for target in named_targets {
+        let (name, len) = target?;
+        match lengths.get(&name) {
+            None => {
+                lengths.insert(name.clone(), len);
+                targets.push((name, len));
+            }
+            Some(&other) if other != len => {
+                return Err(error(
+                    path,
+                    format!("Targets named {name} have different lengths {other} and {len}."),
+                ));
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(targets)
+}
+
+/// The pair of query `q` and target `t`, keyed by `{query},{target}` ID columns.
+fn pair(qi: usize, q: Record, ti: usize, t: Record) -> Pair {
+    let names = (name(qi, &q), name(ti, &t));
+    Pair {
+        key: Some(format!("{},{}", names.0, names.1)),
+        names,
+        a: q,
+        b: t,
+    }
+}
+
+/// Queries from `queries` paired with targets from `targets`, keyed by their names.
+pub fn two_file_pairs(
+    queries: &Path,
+    targets: &Path,
+    pairing: Pairing,
+    format: Option<io::Format>,
+) -> Result<Pairs, io::Error> {
+    let mut query_records = io::open_as(queries, format)?.enumerate();
+    let target_records = io::open_as(targets, format)?;
+    Ok(match pairing {
+        Pairing::Index => {
+            let (queries, targets) = (queries.to_path_buf(), targets.to_path_buf());
+            let longer = |path: &Path, other: &Path| {
+                error(path, format!("Has more records than {}.", other.display()))
+            };
+            Box::new(
+                query_records
+                    .zip_longest(target_records.enumerate())
+                    .map(move |records| match records {
+                        EitherOrBoth::Both((qi, q), (ti, t)) => Ok(pair(qi, q?, ti, t?)),
+                        EitherOrBoth::Left(_) => Err(longer(&queries, &targets)),
+                        EitherOrBoth::Right(_) => Err(longer(&targets, &queries)),
+                    }),
+            )
+        }
+        Pairing::Name => {
+            let mut by_name = HashMap::new();
+            for (ti, t) in target_records.enumerate() {
+                let t = t?;
+                let Some(id) = t.id.clone() else {
+                    return Err(error(targets, format!("Target {ti} has no name.")));
+                };
+                if by_name.insert(id.clone(), (ti, t)).is_some() {
+                    return Err(error(targets, format!("Duplicate target name {id}.")));
+                }
+            }
+            let queries = queries.to_path_buf();
+            Box::new(query_records.map(move |(qi, q)| {
+                let q = q?;
+                let Some((ti, t)) = q.id.as_ref().and_then(|id| by_name.get(id)) else {
+                    return Err(error(
+                        &queries,
+                        format!("No target named {} for query {qi}.", name(qi, &q)),
+                    ));
+                };
+                Ok(pair(qi, q, *ti, t.clone()))
+            }))
+        }
+        Pairing::AllVsAll => {
+            let targets: Vec<Record> = target_records.collect::<Result<_, _>>()?;
+            let targets = Rc::new(targets);
+            Box::new(query_records.flat_map(move |(qi, q)| -> Pairs {
+                let q = match q {
+                    Ok(q) => q,
+                    Err(err) => return Box::new(std::iter::once(Err(err))),
+                };
+                let targets = targets.clone();
+                Box::new(
+                    (0..targets.len())
+                        .map(move |ti| Ok(pair(qi, q.clone(), ti, targets[ti].clone()))),
+                )
+            }))
+        }
+        Pairing::OneVsAll => {
+            let Some((qi, q)) = query_records.next() else {
+                return Err(error(queries, "Contains no query."));
+            };
+            let q = q?;
+            if query_records.next().is_some() {
+                return Err(error(
+                    queries,
+                    "Contains more than one query. Use --pairing all-vs-all instead.",
+                ));
+            }
+            Box::new(
+                target_records
+                    .enumerate()
+                    .map(move |(ti, t)| Ok(pair(qi, q.clone(), ti, t?))),
+            )
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Write `contents` to a file named `name` in the temporary directory.
+    fn write(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("pa-bin-{}-{name}", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn one_vs_all() {
+        let query = write("query.fa", ">q\nACGT\n");
+        let queries = write("queries.fa", ">q1\nACGT\n>q2\nAGT\n");
+        let targets = write("targets.fa", ">t1\nACG\n>t2\nAGT\n");
+        let keys: Vec<_> = two_file_pairs(&query, &targets, Pairing::OneVsAll, None)
+            .unwrap()
+            .map(|pair| pair.unwrap().key.unwrap())
+            .collect();
+        assert_eq!(keys, ["q,t1", "q,t2"]);
+        let err = two_file_pairs(&queries, &targets, Pairing::OneVsAll, None)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.message,
+            "Contains more than one query. Use --pairing all-vs-all instead."
+        );
+        for path in [query, queries, targets] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -1,38 +1,162 @@
-use bio::io::fasta;
-use clap::{value_parser, Args, Parser};
+use clap::builder::RangedU64ValueParser;
+use clap::{value_parser, Args, Parser, Subcommand, ValueEnum};
 use itertools::Itertools;
-use pa_types::{CostModel, Seq};
-use pa_wrapper::Aligner;
-use std::fs::{self, File};
-use std::io::{BufRead, BufWriter, Write};
-use std::path::PathBuf;
+use pa_types::{Cigar, Cost, Pos, Seq};
+#[cfg(feature = "dp")]
+use pa_wrapper::co_optimal;
+use pa_wrapper::io;
+use pa_wrapper::{pretty, Aligner, AlignerParams, AnyAligner, CostPreset, Costs};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::time::Instant;
+
+mod input;
+mod output;
+mod serve;
+use input::{Pair, Pairing};
+use output::{Alignment, CigarStyle};
 
 /// CLI tool that wraps other aligners and runs them on the given input.
 #[derive(Parser)]
-#[command(author)]
+#[command(
+    author,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Return only cost (no traceback).
     #[clap(long)]
     cost_only: bool,
 
-    /// Do not print anything to stderr.
+    /// Do not print progress to stderr. Errors and the --verify and --compare reports are still printed.
     #[clap(long)]
     silent: bool,
 
-    /// (Directory of) .seq, .txt, or Fasta files with sequence pairs to align.
+    /// Skip inputs whose output is complete, and continue partially written outputs.
+    ///
+    /// Outputs are written to `<output>.partial` and renamed when complete. Resume with the
+    /// same arguments as the interrupted run.
+    #[clap(long)]
+    resume: bool,
+
+    /// Report malformed pairs and unreadable files, and continue with the next one.
+    ///
+    /// Without this, pa-bin exits with code 3 on the first malformed input.
+    #[clap(long)]
+    skip_invalid: bool,
+
+    /// Check that each cigar is a valid alignment with the returned cost (or score).
+    ///
+    /// Failing pairs are reported on stderr, and the exit code is 1 when any pair fails.
+    #[clap(long, conflicts_with = "cost_only")]
+    verify: bool,
+
+    /// Yaml/json string of parameters, or the name, of a second aligner to run on the same input.
+    ///
+    /// Pairs where the costs differ are reported on stderr, followed by a summary.
+    /// The exit code is 1 when two exact aligners disagree.
+    #[clap(long, value_name = "PARAMS")]
+    compare: Option<String>,
+
+    /// Append the qualities of both sequences of FASTQ input as `{qual_a},{qual_b}` columns.
+    ///
+    /// Fields are quoted when they contain `,` or `"`. For other formats, the columns are empty.
+    /// For SAM output, the query qualities are always written to the QUAL field.
+    #[clap(long)]
+    qualities: bool,
+
+    /// Append the wall time of each alignment in seconds as a `{time}` column, or `tm` tag.
+    #[clap(long)]
+    time: bool,
+
+    /// Append the aligner stats of each alignment as a `{stats}` column, or `st` tag.
+    ///
+    /// Stats are written as `key=value` pairs separated by `;`, or as an object for jsonl.
+    #[clap(long)]
+    stats: bool,
+
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = output::Format::Csv)]
+    format: output::Format,
+
+    /// Cigar style for tsv, jsonl, PAF, and SAM output. Csv uses the `pa-types` cigar format.
+    #[clap(long, value_enum, default_value_t = CigarStyle::Extended)]
+    cigar_style: CigarStyle,
+
+    /// Print each alignment to stdout. Meant for small inputs.
+    #[clap(long, value_enum, value_name = "VIEW", conflicts_with = "cost_only")]
+    show: Option<Show>,
+
+    /// Number of columns per line for `--show pretty`.
+    #[clap(
+        long,
+        default_value_t = 100,
+        value_name = "COLUMNS",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    show_width: usize,
+
+    /// Print the number of co-optimal alignments of each pair, and up to N of them, to stdout.
+    ///
+    /// This uses a quadratic DP under the given cost model, so is only meant for small inputs.
+    /// Counts that do not fit in 64 bits are printed as `18446744073709551615+`.
+    #[cfg(feature = "dp")]
+    #[clap(long, value_name = "N")]
+    co_optimal: Option<usize>,
+
+    /// (Directory of) .seq, .txt, FASTA, or FASTQ files with sequence pairs to align.
     ///
-    /// For directories, this is not recursive. Only files in the directory itself are processed.
-    #[clap(value_parser = value_parser!(PathBuf), display_order = 1, required_unless_present = "print_params")]
+    /// Files may be compressed with gzip, bgzip, or zstd. The format is detected from the
+    /// extension, or from the contents when the extension is unknown.
+    /// For directories, this is not recursive. Only files in the directory itself with a
+    /// sequence file extension are processed.
+    /// Use `-` to read from stdin, which is streamed and written to stdout by default.
+    #[clap(value_parser = value_parser!(PathBuf), display_order = 1, required_unless_present_any = ["print_params", "list_aligners"])]
     input: Option<PathBuf>,
 
-    /// Write a .csv of `{cost},{cigar}` lines. Defaults to input file with .csv extension.
+    /// Write a .csv of `{cost},{cigar}` lines. Defaults to the input file with the extension of --format.
+    ///
+    /// For extension aligners, lines are `{score},{a_end},{b_end},{cigar}` instead.
+    /// With --ids, lines start with an `{id},` column.
+    /// Other formats are written with --format.
     ///
     /// If input is a file, output is written to this file. If input is a directory, output is
     /// written to a file in this directory with the same name as the input file.
+    /// Use `-` to write to stdout, flushed after every pair.
     #[clap(value_parser = value_parser!(PathBuf), display_order = 1)]
     output: Option<PathBuf>,
 
+    /// File with target sequences. INPUT then contains query sequences instead of pairs.
+    ///
+    /// Output lines start with `{query},{target}` columns, with the record names or 0-based indices.
+    #[clap(long, value_name = "PATH", display_order = 1)]
+    targets: Option<PathBuf>,
+
+    /// How to pair queries in INPUT with the sequences in --targets.
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = Pairing::Index,
+        requires = "targets",
+        display_order = 1
+    )]
+    pairing: Pairing,
+
+    /// Format of the input files, instead of detecting it from the extension or contents.
+    #[clap(long, value_enum, value_name = "FORMAT", display_order = 1)]
+    input_format: Option<io::Format>,
+
+    /// Start csv lines with an `{id},` column: the ID of the pair, or its 0-based index.
+    ///
+    /// With --targets, lines always start with `{query},{target}` columns instead.
+    #[clap(long, conflicts_with = "targets", display_order = 1)]
+    ids: bool,
+
     #[clap(flatten, next_help_heading = "Aligner")]
     aligner: AlignerArgs,
 
@@ -42,7 +166,35 @@ struct Cli {
 
     /// Whether to return a traceback.
     #[clap(flatten, next_help_heading = "Cost model")]
-    cost_model: CostModel,
+    cost_model: Costs,
+
+    /// Use the cost model of a common tool instead of the options above.
+    ///
+    /// Presets with a match bonus are run on the equivalent costs without match bonus.
+    #[clap(
+        long,
+        value_enum,
+        value_name = "PRESET",
+        help_heading = "Cost model",
+        conflicts_with_all = ["sub", "open", "extend", "ins_open", "ins_extend", "del_open", "del_extend", "match_bonus"]
+    )]
+    cost_preset: Option<CostPreset>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Answer alignment requests sent as json lines on stdin or a Unix socket.
+    ///
+    /// Built aligners are kept alive and reused for requests with the same parameters and costs.
+    Serve(serve::ServeArgs),
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Show {
+    /// Three-line view of both sequences, colored when stdout is a terminal.
+    Pretty,
+    /// The list of positions `(i, j)` on the alignment path.
+    Path,
 }
 
 #[derive(Args)]
@@ -68,27 +220,173 @@ struct AlignerArgs {
     /// Print default parameters for the given aligner.
     #[clap(long, value_name = "ALIGNER")]
     print_params: Option<Aligner>,
+
+    /// Print name, version, SIMD target, and citation of all enabled aligners.
+    #[clap(long)]
+    list_aligners: bool,
+}
+
+/// Exit code for invalid arguments or aligner parameters, as used by clap.
+const EXIT_USAGE: i32 = 2;
+/// Exit code for unreadable or malformed input, unless `--skip-invalid` is given.
+const EXIT_INVALID_INPUT: i32 = 3;
+/// Exit code for failing to write output, or to read other files.
+const EXIT_IO: i32 = 4;
+
+/// Print `message` to stderr and exit with `code`.
+fn fail(code: i32, message: impl std::fmt::Display) -> ! {
+    eprintln!("{message}");
+    exit(code)
+}
+
+/// Exit with [`EXIT_IO`] for an IO error on `path`.
+fn io_failure<T>(path: &Path) -> impl Fn(std::io::Error) -> T + '_ {
+    move |err| fail(EXIT_IO, format!("{}: {err}", path.display()))
+}
+
+/// Parse yaml or json aligner parameters.
+fn parse_params(params: &str, json: bool) -> AlignerParams {
+    let result = if json {
+        serde_json::from_str(params).map_err(|err| err.to_string())
+    } else {
+        serde_yaml::from_str(params).map_err(|err| err.to_string())
+    };
+    result.unwrap_or_else(|err| fail(EXIT_USAGE, format!("Failed to parse parameters: {err}")))
+}
+
+/// Build the aligner for `params`, or exit for unsupported parameters.
+fn build_aligner(params: &AlignerParams, costs: Costs, trace: bool) -> AnyAligner {
+    params
+        .try_build_any_aligner(costs, trace, 0)
+        .unwrap_or_else(|err| {
+            fail(
+                EXIT_USAGE,
+                format!("Bad aligner parameters {params:?}: {err}"),
+            )
+        })
+}
+
+/// Check that `cigar` is a valid alignment of `a` and `b` with the returned cost,
+/// or with the returned score of an extension ending at `ends`.
+fn verify(
+    costs: &Costs,
+    cost: Cost,
+    ends: Option<(usize, usize)>,
+    cigar: &Cigar,
+    a: Seq,
+    b: Seq,
+) -> Result<(), String> {
+    let Some((a_end, b_end)) = ends else {
+        let cigar_cost = costs.verify(cigar, a, b)?;
+        if cigar_cost != cost {
+            return Err(format!(
+                "Returned cost {cost} but cigar {cigar} has cost {cigar_cost}."
+            ));
+        }
+        return Ok(());
+    };
+    let (Some(a), Some(b)) = (a.get(..a_end), b.get(..b_end)) else {
+        return Err(format!(
+            "Extension ends at ({a_end}, {b_end}), past the end of the sequences."
+        ));
+    };
+    let score = costs.score(costs.verify(cigar, a, b)?, a.len(), b.len());
+    if score != cost {
+        return Err(format!(
+            "Returned score {cost} but cigar {cigar} has score {score}."
+        ));
+    }
+    Ok(())
+}
+
+/// Agreement of the costs of the aligner and the one given by --compare.
+#[derive(Default)]
+struct Comparison {
+    pairs: usize,
+    disagreements: usize,
+    /// Pairs where the aligner found the lower cost (or higher score).
+    better: usize,
+    /// Pairs where the compared aligner found the lower cost (or higher score).
+    worse: usize,
+    /// Pairs where either aligner gave up and returned `Cost::MAX`, which are not compared.
+    no_result: usize,
+    max_difference: u32,
+}
+
+/// Totals over all pairs, printed at the end of the run.
+#[derive(Default)]
+struct Summary {
+    pairs: usize,
+    bases: usize,
+    /// Total alignment time in seconds, excluding IO.
+    time: f64,
+    costs: Vec<Cost>,
+    /// Pairs where the aligner gave up and returned `Cost::MAX`, which are not in `costs`.
+    no_result: usize,
+}
+
+impl Summary {
+    fn add(&mut self, bases: usize, cost: Cost, time: f64) {
+        self.pairs += 1;
+        self.bases += bases;
+        self.time += time;
+        if cost == Cost::MAX {
+            self.no_result += 1;
+        } else {
+            self.costs.push(cost);
+        }
+    }
+
+    fn print(mut self, name: &str, is_extension: bool) {
+        if self.pairs == 0 {
+            return;
+        }
+        eprintln!(
+            "{name} aligned {} pairs with {} bases in {:.3}s: {:.0} pairs/s, {:.3} Mbp/s.",
+            self.pairs,
+            self.bases,
+            self.time,
+            self.pairs as f64 / self.time,
+            self.bases as f64 / self.time / 1e6,
+        );
+        if self.no_result > 0 {
+            eprintln!("{} pairs without a result.", self.no_result);
+        }
+        if self.costs.is_empty() {
+            return;
+        }
+        self.costs.sort();
+        let n = self.costs.len();
+        let quantile = |q: f64| self.costs[((n - 1) as f64 * q).round() as usize];
+        let mean = self.costs.iter().map(|&c| c as f64).sum::<f64>() / n as f64;
+        eprintln!(
+            "{}: min {}, median {}, mean {mean:.2}, p90 {}, p99 {}, max {}.",
+            if is_extension { "Scores" } else { "Costs" },
+            quantile(0.0),
+            quantile(0.5),
+            quantile(0.9),
+            quantile(0.99),
+            quantile(1.0),
+        );
+    }
 }
 
 fn main() {
     let args = Cli::parse();
 
+    if let Some(Command::Serve(serve_args)) = args.command {
+        serve::serve(serve_args);
+        return;
+    }
+
     // Exactly one of these will be true because of the AlignerArgs group.
     let aligner_params = if let Some(aligner) = args.aligner.aligner {
         aligner.default_params()
-    } else if let Some(params) = args.aligner.params {
-        if args.json {
-            serde_json::from_str(&params).expect("Failed to parse parameters as json")
-        } else {
-            serde_yaml::from_str(&params).expect("Failed to parse parameters as yaml")
-        }
-    } else if let Some(params_file) = args.aligner.params_file {
-        let params = fs::read_to_string(params_file).unwrap();
-        if args.json {
-            serde_json::from_str(&params).expect("Failed to parse parameters as json")
-        } else {
-            serde_yaml::from_str(&params).expect("Failed to parse parameters as yaml")
-        }
+    } else if let Some(params) = &args.aligner.params {
+        parse_params(params, args.json)
+    } else if let Some(params_file) = &args.aligner.params_file {
+        let params = fs::read_to_string(params_file).unwrap_or_else(io_failure(params_file));
+        parse_params(&params, args.json)
     } else if let Some(aligner) = args.aligner.print_params {
         let params = aligner.default_params();
         if args.json {
@@ -97,50 +395,132 @@ fn main() {
             println!("{}", serde_yaml::to_string(&params).unwrap());
         }
         exit(0);
+    } else if args.aligner.list_aligners {
+        let infos: Vec<_> = Aligner::value_variants()
+            .iter()
+            .map(|aligner| aligner.default_params().info())
+            .collect();
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&infos).unwrap());
+        } else {
+            println!("{}", serde_yaml::to_string(&infos).unwrap());
+        }
+        exit(0);
     } else {
         unreachable!()
     };
 
     let Some(input) = args.input else {
-        eprintln!("Input is required");
-        exit(1);
+        fail(EXIT_USAGE, "Input is required");
     };
 
-    let mut aligner = aligner_params
-        .build_aligner(args.cost_model, !args.cost_only, 0)
-        .0;
+    let cost_model = args.cost_preset.map_or(args.cost_model, CostPreset::costs);
+    let is_extension = aligner_params.is_extension();
+    let mut aligner = build_aligner(&aligner_params, cost_model, !args.cost_only);
+    let compare_params =
+        args.compare
+            .as_ref()
+            .map(|params| match Aligner::from_str(params, true) {
+                Ok(aligner) => aligner.default_params(),
+                Err(_) => parse_params(params, args.json),
+            });
+    if compare_params
+        .as_ref()
+        .is_some_and(|p| p.is_extension() != is_extension)
+    {
+        fail(
+            EXIT_USAGE,
+            "--compare must use an extension aligner exactly when the aligner does",
+        );
+    }
+    let mut compare_aligner = compare_params
+        .as_ref()
+        .map(|p| build_aligner(p, cost_model, false));
+    let (aligner_name, compare_name) = (
+        aligner_params.info().name,
+        compare_params.as_ref().map(|p| p.info().name),
+    );
+    let mut failed_verifications = 0;
+    let mut comparison = Comparison::default();
+    let stdio = Path::new("-");
+    if input == stdio && args.targets.as_deref() == Some(stdio) {
+        fail(
+            EXIT_USAGE,
+            "Input and --targets cannot both be read from stdin",
+        );
+    }
+    if args.format == output::Format::Sam
+        && (input == stdio || args.targets.as_deref() == Some(stdio))
+    {
+        fail(
+            EXIT_USAGE,
+            "--format sam lists the targets in its header, so they cannot be read from stdin",
+        );
+    }
+    if args.format == output::Format::Paf && args.cost_only {
+        fail(
+            EXIT_USAGE,
+            "--format paf needs a cigar for its match and block length columns, so it cannot be used with --cost-only",
+        );
+    }
+    if input != stdio && !input.exists() {
+        fail(
+            EXIT_INVALID_INPUT,
+            format!("{}: No such file or directory", input.display()),
+        );
+    }
     // Parse file
-    let files = if input.is_file() {
-        let output = args.output.unwrap_or(input.with_extension("csv"));
+    let files = if args.targets.is_some() && !(input.is_file() || input == stdio) {
+        fail(EXIT_USAGE, "Input must be a file when --targets is given");
+    } else if input == stdio {
+        vec![(input.clone(), args.output.clone().unwrap_or(stdio.into()))]
+    } else if input.is_file() {
+        let output = args
+            .output
+            .clone()
+            .unwrap_or(input.with_extension(args.format.extension()));
         vec![(input.clone(), output)]
     } else {
         if let Some(output) = args.output.as_ref() {
             if output.exists() && !output.is_dir() {
-                eprintln!("Output must be a directory if input is a directory");
-                exit(1);
+                fail(
+                    EXIT_USAGE,
+                    "Output must be a directory if input is a directory",
+                );
             }
             if !output.exists() {
                 eprintln!("Creating output directory {}", output.display());
-                fs::create_dir_all(&output).unwrap();
+                fs::create_dir_all(output).unwrap_or_else(io_failure(output));
             }
         }
         input
             .read_dir()
-            .expect(&format!("{} is not a file or directory", input.display()))
+            .unwrap_or_else(io_failure(&input))
             .filter_map(|x| {
-                let x = x.unwrap();
-                if !x.file_type().unwrap().is_file() {
+                let x = x.unwrap_or_else(io_failure(&input));
+                if !x
+                    .file_type()
+                    .unwrap_or_else(io_failure(&x.path()))
+                    .is_file()
+                {
                     return None;
                 }
-                if !["fna", "fa", "fasta", "seq", "txt"]
-                    .contains(&x.path().extension().unwrap_or_default().to_str().unwrap())
-                {
+                // Skip outputs of earlier runs, which may look like sequence files.
+                if output::is_output(&x.path()) {
                     return None;
                 }
+                // Select sequence files by extension only, since the contents of other
+                // files, e.g. a README or script, may look like sequences.
+                io::Format::from_path(&x.path())?;
                 let input = x.path();
                 let output = match args.output.clone() {
-                    Some(o) => o.join(input.with_extension("csv").file_name().unwrap()),
-                    None => input.with_extension("csv"),
+                    Some(o) => o.join(
+                        input
+                            .with_extension(args.format.extension())
+                            .file_name()
+                            .unwrap(),
+                    ),
+                    None => input.with_extension(args.format.extension()),
                 };
 
                 Some((input, output))
@@ -148,62 +528,257 @@ fn main() {
             .collect()
     };
 
+    // Alignments and co-optimal paths are printed to stdout as well.
+    #[cfg(feature = "dp")]
+    let prints = args.show.is_some() || args.co_optimal.is_some();
+    #[cfg(not(feature = "dp"))]
+    let prints = args.show.is_some();
+    if prints && files.iter().any(|(_, o)| o == stdio) {
+        fail(
+            EXIT_USAGE,
+            "--show and --co-optimal cannot be used when writing output to stdout",
+        );
+    }
+
+    let color = std::io::stdout().is_terminal();
+    let show = |cigar: &Cigar, a: Seq, b: Seq| match args.show {
+        None => {}
+        Some(Show::Pretty) => print!(
+            "{}",
+            pretty::pretty_alignment(cigar, a, b, args.show_width, color)
+        ),
+        Some(Show::Path) => println!(
+            "{}",
+            pretty::cigar_to_path(cigar)
+                .iter()
+                .map(|Pos(i, j)| format!("({i},{j})"))
+                .join(" ")
+        ),
+    };
+
+    let writer = output::Writer {
+        format: args.format,
+        cigar_style: args.cigar_style,
+        qualities: args.qualities,
+        time: args.time,
+        stats: args.stats,
+    };
+    let mut summary = Summary::default();
+
     let mut done = 0;
+    let (mut skipped_files, mut skipped_pairs) = (0, 0);
     for (i, o) in files {
         let header = format!("{} => {}", i.display(), o.display());
 
         // Process the input.
-        let mut run_pair = |a: Seq, b: Seq, o: &mut BufWriter<File>| {
-            let (cost, cigar, _stats) = aligner.align(a, b);
+        let to_stdout = o == stdio;
+        if args.resume && !to_stdout && o.exists() {
+            if !args.silent {
+                eprintln!("Skipping {}: {} is complete", i.display(), o.display());
+            }
+            continue;
+        }
+        let mut run_pair = |pair: &Pair, o: &mut dyn Write| -> std::io::Result<()> {
+            let (a, b) = (&pair.a.seq[..], &pair.b.seq[..]);
+            let start = Instant::now();
+            let (cost, ends, cigar, stats) = match &mut aligner {
+                AnyAligner::Extension(aligner) => {
+                    let (extension, cigar, stats) = aligner.align_extension(a, b);
+                    (
+                        extension.score,
+                        Some((extension.a_end, extension.b_end)),
+                        cigar,
+                        stats,
+                    )
+                }
+                AnyAligner::Global(aligner) => {
+                    let (cost, cigar, stats) = aligner.align(a, b);
+                    (cost, None, cigar, stats)
+                }
+            };
+            let time = start.elapsed().as_secs_f64();
+            summary.add(a.len() + b.len(), cost, time);
+
+            if let Some(cigar) = &cigar {
+                match ends {
+                    Some((a_end, b_end)) => show(cigar, &a[..a_end], &b[..b_end]),
+                    None => show(cigar, a, b),
+                }
+            }
+            #[cfg(feature = "dp")]
+            if !is_extension {
+                if let Some(n) = args.co_optimal {
+                    let (optimal_cost, count) = co_optimal::count_co_optimal(cost_model, a, b);
+                    let saturated = if count == u64::MAX { "+" } else { "" };
+                    let is_optimal = if cost == optimal_cost { "" } else { "not " };
+                    println!(
+                        "Optimal cost {optimal_cost} with {count}{saturated} co-optimal alignments. Returned cost {cost} is {is_optimal}optimal."
+                    );
+                    let (_, cigars) = co_optimal::co_optimal_alignments(cost_model, a, b, n);
+                    for cigar in &cigars {
+                        println!("{cigar}");
+                        show(cigar, a, b);
+                    }
+                }
+            }
+
+            let label = pair.key.as_ref().unwrap_or(&pair.names.0);
+            if args.verify {
+                if let Some(cigar) = &cigar {
+                    if let Err(err) = verify(&cost_model, cost, ends, cigar, a, b) {
+                        failed_verifications += 1;
+                        eprintln!("\nPair {label}: {err}");
+                    }
+                }
+            }
+            if let Some(compare_aligner) = &mut compare_aligner {
+                let compare_cost = match compare_aligner {
+                    AnyAligner::Extension(aligner) => aligner.align_extension(a, b).0.score,
+                    AnyAligner::Global(aligner) => aligner.align(a, b).0,
+                };
+                comparison.pairs += 1;
+                if cost == Cost::MAX || compare_cost == Cost::MAX {
+                    comparison.no_result += 1;
+                } else if compare_cost != cost {
+                    comparison.disagreements += 1;
+                    // Extension aligners maximize the score instead of minimizing the cost.
+                    if (cost < compare_cost) != is_extension {
+                        comparison.better += 1;
+                    } else {
+                        comparison.worse += 1;
+                    }
+                    comparison.max_difference =
+                        comparison.max_difference.max(cost.abs_diff(compare_cost));
+                    eprintln!(
+                        "\nPair {label}: {aligner_name} returned {cost} but {} returned {compare_cost}",
+                        compare_name.as_ref().unwrap()
+                    );
+                }
+            }
 
             done += 1;
             if !args.silent {
                 eprint!("\rDone {done:>6}: {header}",);
             }
 
-            writeln!(
-                o,
-                "{cost},{}",
-                cigar.map_or(String::new(), |c| c.to_string())
-            )
-            .unwrap();
+            let line = writer.line(&Alignment {
+                pair,
+                cost,
+                ends,
+                cigar: cigar.as_ref(),
+                time,
+                stats: &stats,
+            });
+            writeln!(o, "{line}")?;
+            if to_stdout {
+                o.flush()?;
+            }
+            Ok(())
         };
 
-        let ext = i.extension().expect("Unknown file extension");
-        let i = std::io::BufReader::new(std::fs::File::open(&i).unwrap());
-        let mut o = std::io::BufWriter::new(std::fs::File::create(o).unwrap());
-        match ext {
-            ext if ext == "seq" || ext == "txt" => {
-                for (a, b) in i.lines().map(|l| l.unwrap().into_bytes()).tuples() {
-                    let mut a = &a[..];
-                    let mut b = &b[..];
-                    if ext == "seq" {
-                        let (&a0, ar) = a.split_first().unwrap();
-                        a = ar;
-                        assert_eq!(
-                            a0 as char, '>',
-                            "In a .seq file, lines must alternating start with '>' and '<'."
-                        );
-                        let (&b0, br) = b.split_first().unwrap();
-                        b = br;
-                        assert_eq!(
-                            b0 as char, '<',
-                            "In a .seq file, lines must alternating start with '>' and '<'."
-                        );
-                    }
-                    run_pair(&a, &b, &mut o);
-                }
+        let pairs = match &args.targets {
+            Some(targets) => input::two_file_pairs(&i, targets, args.pairing, args.input_format),
+            None => input::file_pairs(&i, args.input_format, args.ids),
+        };
+        // SAM headers list the targets, so they are read up front.
+        let targets = match args.format {
+            output::Format::Sam => {
+                input::target_lengths(&i, args.targets.as_deref(), args.input_format)
             }
-            ext if ext == "fna" || ext == "fa" || ext == "fasta" => {
-                for (a, b) in fasta::Reader::new(i).records().tuples() {
-                    run_pair(a.unwrap().seq(), b.unwrap().seq(), &mut o);
-                }
+            _ => Ok(vec![]),
+        };
+        let (pairs, targets) = match pairs.and_then(|pairs| Ok((pairs, targets?))) {
+            Ok(pairs) => pairs,
+            Err(err) if args.skip_invalid => {
+                eprintln!("\n{err}");
+                skipped_files += 1;
+                continue;
+            }
+            Err(err) => fail(EXIT_INVALID_INPUT, err),
+        };
+        let file_header = writer.header(&aligner_params, &cost_model, is_extension, &targets);
+        // Files are written to a temporary file that is renamed when complete,
+        // so that partial outputs are never mistaken for finished ones.
+        let partial = output::partial_path(&o);
+        let resumed = if args.resume && !to_stdout && partial.exists() {
+            output::resume_partial(&partial, file_header.lines().count())
+                .unwrap_or_else(io_failure(&partial))
+        } else {
+            None
+        };
+        let write_path = if to_stdout { &o } else { &partial };
+        let write_failed = io_failure::<()>(write_path);
+        let mut out: Box<dyn Write> = if to_stdout {
+            Box::new(BufWriter::new(std::io::stdout().lock()))
+        } else {
+            let file = match resumed {
+                Some(_) => OpenOptions::new().append(true).open(&partial),
+                None => File::create(&partial),
+            };
+            Box::new(BufWriter::new(file.unwrap_or_else(io_failure(&partial))))
+        };
+        let mut to_skip = match resumed {
+            Some(n) => {
+                eprintln!("Resuming {} after {n} pairs", o.display());
+                n
             }
-            ext => {
-                unreachable!("Unknown file extension {ext:?}. Must be in {{seq,txt,fna,fa,fasta}}.")
+            None => {
+                write!(out, "{file_header}")
+                    .and_then(|_| out.flush())
+                    .unwrap_or_else(&write_failed);
+                0
             }
         };
+        for pair in pairs {
+            match pair {
+                // Pairs that were already written by an interrupted run.
+                Ok(_) if to_skip > 0 => to_skip -= 1,
+                Ok(pair) => run_pair(&pair, &mut *out).unwrap_or_else(&write_failed),
+                Err(err) if args.skip_invalid => {
+                    eprintln!("\nSkipping invalid input: {err}");
+                    skipped_pairs += 1;
+                }
+                Err(err) => fail(EXIT_INVALID_INPUT, format!("\n{err}")),
+            }
+        }
+        out.flush().unwrap_or_else(&write_failed);
+        drop(out);
+        if !to_stdout {
+            fs::rename(&partial, &o).unwrap_or_else(io_failure(&o));
+        }
     }
 
     eprintln!();
+
+    if skipped_files > 0 || skipped_pairs > 0 {
+        eprintln!("Skipped {skipped_files} unreadable files and {skipped_pairs} invalid pairs.");
+    }
+    if !args.silent {
+        summary.print(&aligner_name, is_extension);
+    }
+    if args.verify {
+        eprintln!("Verified {done} pairs: {failed_verifications} failed.");
+    }
+    if let Some(compare_name) = &compare_name {
+        let Comparison {
+            pairs,
+            disagreements,
+            better,
+            worse,
+            no_result,
+            max_difference,
+        } = comparison;
+        eprintln!(
+            "Compared {aligner_name} to {compare_name} on {pairs} pairs: {disagreements} disagree, \
+             {aligner_name} is better on {better} and {compare_name} on {worse}, \
+             with a maximum difference of {max_difference}. \
+             {no_result} pairs without a result were not compared."
+        );
+    }
+    let exact_disagree = comparison.disagreements > 0
+        && aligner_params.is_exact()
+        && compare_params.as_ref().is_some_and(|p| p.is_exact());
+    if failed_verifications > 0 || exact_disagree {
+        exit(1);
+    }
 }
@@ -0,0 +1,410 @@
+//! Output formats of `pa-bin`.
+//!
+//! The query `a` of each pair is treated as the read and the target `b` as the reference,
+//! so in SAM and PAF cigars `I` consumes only the query and `D` only the target.
+
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use itertools::Itertools;
+use pa_types::{Cigar, CigarElem, CigarOp, Cost};
+use pa_wrapper::io;
+use pa_wrapper::{AlignerParams, AlignerStats, Costs};
+use serde_json::json;
+
+use crate::input::Pair;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// `{cost},{cigar}` lines, or `{score},{a_end},{b_end},{cigar}` for extension aligners.
+    Csv,
+    /// Tab-separated columns with a header.
+    Tsv,
+    /// One json object per line.
+    Jsonl,
+    /// Pairwise mapping format, with `NM`, `AS`, and `cg` tags. Not with --cost-only.
+    Paf,
+    /// Sequence alignment/map format, with `NM` and `AS` tags and the targets in its header.
+    Sam,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Tsv => "tsv",
+            Format::Jsonl => "jsonl",
+            Format::Paf => "paf",
+            Format::Sam => "sam",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CigarStyle {
+    /// `=` for matches and `X` for substitutions.
+    Extended,
+    /// `M` for both matches and substitutions.
+    Match,
+}
+
+/// The result of aligning one pair.
+pub struct Alignment<'a> {
+    pub pair: &'a Pair,
+    /// The cost, or the score for extension alignment.
+    pub cost: Cost,
+    /// For extension alignment, the end of the alignment in the query and target.
+    pub ends: Option<(usize, usize)>,
+    pub cigar: Option<&'a Cigar>,
+    /// Wall time of the alignment in seconds.
+    pub time: f64,
+    pub stats: &'a AlignerStats,
+}
+
+pub struct Writer {
+    pub format: Format,
+    pub cigar_style: CigarStyle,
+    /// Append the qualities of the query and target.
+    pub qualities: bool,
+    /// Append the wall time of each alignment.
+    pub time: bool,
+    /// Append the aligner stats of each alignment.
+    pub stats: bool,
+}
+
+/// The temporary file that output is written to until it is complete.
+pub fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".partial");
+    name.into()
+}
+
+/// Whether `path` is a (partial) output file, judging by its extension.
+pub fn is_output(path: &Path) -> bool {
+    let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
+        return false;
+    };
+    ext == "partial"
+        || Format::value_variants()
+            .iter()
+            .any(|f| f.extension() == ext)
+}
+
+/// Prepare the partial output `path` of an interrupted run to be continued.
+///
+/// Truncates the file to its last complete line, and returns the number of pair lines after
+/// the `header_lines` header lines, or `None` when not even the header is complete.
+pub fn resume_partial(path: &Path, header_lines: usize) -> std::io::Result<Option<usize>> {
+    let data = fs::read(path)?;
+    let complete = data.iter().rposition(|&c| c == b'\n').map_or(0, |p| p + 1);
+    let lines = data[..complete].iter().filter(|&&c| c == b'\n').count();
+    if lines < header_lines {
+        return Ok(None);
+    }
+    OpenOptions::new()
+        .write(true)
+        .open(path)?
+        .set_len(complete as u64)?;
+    Ok(Some(lines - header_lines))
+}
+
+/// The cigar in SAM convention.
+fn sam_cigar(cigar: &Cigar, style: CigarStyle) -> String {
+    let mut ops: Vec<(char, usize)> = vec![];
+    for CigarElem { op, cnt } in &cigar.ops {
+        let c = match (op, style) {
+            (CigarOp::Match, CigarStyle::Extended) => '=',
+            (CigarOp::Sub, CigarStyle::Extended) => 'X',
+            (CigarOp::Match | CigarOp::Sub, CigarStyle::Match) => 'M',
+            (CigarOp::Del, _) => 'I',
+            (CigarOp::Ins, _) => 'D',
+        };
+        match ops.last_mut() {
+            Some((last, n)) if *last == c => *n += *cnt as usize,
+            _ => ops.push((c, *cnt as usize)),
+        }
+    }
+    ops.iter().map(|(c, n)| format!("{n}{c}")).collect()
+}
+
+/// The number of matches, the edit distance (`NM`), and the number of columns of a cigar.
+fn cigar_counts(cigar: &Cigar) -> (usize, usize, usize) {
+    let (mut matches, mut nm, mut len) = (0, 0, 0);
+    for CigarElem { op, cnt } in &cigar.ops {
+        let cnt = *cnt as usize;
+        match op {
+            CigarOp::Match => matches += cnt,
+            _ => nm += cnt,
+        }
+        len += cnt;
+    }
+    (matches, nm, len)
+}
+
+/// Quote a csv field when it contains `,` or `"`.
+fn csv_field(field: &[u8]) -> String {
+    let field = String::from_utf8_lossy(field);
+    if field.contains([',', '"']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.into_owned()
+    }
+}
+
+/// Aligner stats as `key=value` pairs sorted by key and separated by `;`.
+fn stats_field(stats: &AlignerStats) -> String {
+    let mut stats: Vec<_> = stats.iter().collect();
+    stats.sort_by(|a, b| a.0.cmp(b.0));
+    stats.iter().map(|(k, v)| format!("{k}={v}")).join(";")
+}
+
+fn qual(record: &io::Record) -> String {
+    String::from_utf8_lossy(record.qual.as_deref().unwrap_or_default()).into_owned()
+}
+
+impl Writer {
+    /// Header lines with the aligner parameters and cost model. PAF and csv have no header.
+    ///
+    /// For SAM, the names and lengths of the `targets` are listed as reference sequences.
+    pub fn header(
+        &self,
+        params: &AlignerParams,
+        costs: &Costs,
+        is_extension: bool,
+        targets: &[(String, usize)],
+    ) -> String {
+        let params_json = serde_json::to_string(params).unwrap();
+        let costs_json = serde_json::to_string(costs).unwrap();
+        let mut header = String::new();
+        match self.format {
+            Format::Csv | Format::Paf => {}
+            Format::Tsv => {
+                header += &format!("# aligner: {params_json}\n# costs: {costs_json}\n");
+                header += if is_extension {
+                    "query\ttarget\tscore\tquery_end\ttarget_end\tcigar"
+                } else {
+                    "query\ttarget\tcost\tcigar"
+                };
+                if self.qualities {
+                    header += "\tquery_qual\ttarget_qual";
+                }
+                if self.time {
+                    header += "\ttime";
+                }
+                if self.stats {
+                    header += "\tstats";
+                }
+                header += "\n";
+            }
+            Format::Jsonl => {
+                header += &json!({ "aligner": params, "costs": costs }).to_string();
+                header += "\n";
+            }
+            Format::Sam => {
+                header += "@HD\tVN:1.6\tSO:unsorted\n";
+                for (name, len) in targets {
+                    header += &format!("@SQ\tSN:{name}\tLN:{len}\n");
+                }
+                header += &format!(
+                    "@PG\tID:pa-bin\tPN:pa-bin\tVN:{}\tCL:{}\n",
+                    env!("CARGO_PKG_VERSION"),
+                    std::env::args().collect::<Vec<_>>().join(" ")
+                );
+                header += &format!("@CO\taligner: {params_json}\n@CO\tcosts: {costs_json}\n");
+            }
+        }
+        header
+    }
+
+    /// The `tm` (time) and `st` (stats) tags of PAF and SAM lines.
+    fn tags(&self, time: f64, stats: &AlignerStats) -> String {
+        let mut tags = String::new();
+        if self.time {
+            tags += &format!("\ttm:f:{time}");
+        }
+        if self.stats {
+            tags += &format!("\tst:Z:{}", stats_field(stats));
+        }
+        tags
+    }
+
+    /// The output line for `aln`, without trailing newline.
+    pub fn line(&self, aln: &Alignment) -> String {
+        let Alignment {
+            pair,
+            cost,
+            ends,
+            cigar,
+            time,
+            stats,
+        } = *aln;
+        let (query, target) = (&pair.names.0, &pair.names.1);
+        let (a, b) = (&pair.a, &pair.b);
+        let cigar_string = cigar.map(|c| sam_cigar(c, self.cigar_style));
+        // Aligners return `Cost::MAX` when they give up, e.g. when the pair is out of band.
+        let unmapped = cost == Cost::MAX;
+        match self.format {
+            Format::Csv => {
+                let cigar = cigar.map_or(String::new(), |c| c.to_string());
+                let mut line = match ends {
+                    Some((a_end, b_end)) => format!("{cost},{a_end},{b_end},{cigar}"),
+                    None => format!("{cost},{cigar}"),
+                };
+                if let Some(key) = &pair.key {
+                    line = format!("{key},{line}");
+                }
+                if self.qualities {
+                    for record in [a, b] {
+                        line.push(',');
+                        line.push_str(&csv_field(record.qual.as_deref().unwrap_or_default()));
+                    }
+                }
+                if self.time {
+                    line += &format!(",{time}");
+                }
+                if self.stats {
+                    line += &format!(",{}", stats_field(stats));
+                }
+                line
+            }
+            Format::Tsv => {
+                let cigar = cigar_string.unwrap_or_default();
+                let mut line = match ends {
+                    Some((a_end, b_end)) => {
+                        format!("{query}\t{target}\t{cost}\t{a_end}\t{b_end}\t{cigar}")
+                    }
+                    None => format!("{query}\t{target}\t{cost}\t{cigar}"),
+                };
+                if self.qualities {
+                    line += &format!("\t{}\t{}", qual(a), qual(b));
+                }
+                if self.time {
+                    line += &format!("\t{time}");
+                }
+                if self.stats {
+                    line += &format!("\t{}", stats_field(stats));
+                }
+                line
+            }
+            Format::Jsonl => {
+                let mut line = json!({ "query": query, "target": target, "cigar": cigar_string });
+                match ends {
+                    Some((a_end, b_end)) => {
+                        line["score"] = json!(cost);
+                        line["query_end"] = json!(a_end);
+                        line["target_end"] = json!(b_end);
+                    }
+                    None => line["cost"] = json!(cost),
+                }
+                if self.qualities {
+                    line["query_qual"] = json!(a.qual.as_ref().map(|_| qual(a)));
+                    line["target_qual"] = json!(b.qual.as_ref().map(|_| qual(b)));
+                }
+                if self.time {
+                    line["time"] = json!(time);
+                }
+                if self.stats {
+                    line["stats"] = json!(stats);
+                }
+                line.to_string()
+            }
+            Format::Paf if unmapped => {
+                // Like minimap2 --paf-no-hit: no strand and target.
+                let mut line = format!("{query}\t{}\t0\t0\t*\t*\t0\t0\t0\t0\t0\t0", a.seq.len());
+                line += &self.tags(time, stats);
+                line
+            }
+            Format::Paf => {
+                let (a_end, b_end) = ends.unwrap_or((a.seq.len(), b.seq.len()));
+                let score = if ends.is_some() { cost } else { -cost };
+                let (matches, nm, len) = cigar.map_or((0, 0, 0), cigar_counts);
+                let mut line = format!(
+                    "{query}\t{}\t0\t{a_end}\t+\t{target}\t{}\t0\t{b_end}\t{matches}\t{len}\t255\tAS:i:{score}",
+                    a.seq.len(),
+                    b.seq.len(),
+                );
+                if let Some(cigar_string) = cigar_string {
+                    line += &format!("\tNM:i:{nm}\tcg:Z:{cigar_string}");
+                }
+                line += &self.tags(time, stats);
+                line
+            }
+            Format::Sam => {
+                let score = if ends.is_some() { cost } else { -cost };
+                let cigar_string = match (cigar_string, ends) {
+                    (None, _) => "*".to_string(),
+                    // Soft-clip the unaligned end of the query.
+                    (Some(c), Some((a_end, _))) if a_end < a.seq.len() => {
+                        format!("{c}{}S", a.seq.len() - a_end)
+                    }
+                    (Some(c), _) => c,
+                };
+                let seq = if a.seq.is_empty() {
+                    "*".to_string()
+                } else {
+                    String::from_utf8_lossy(&a.seq).into_owned()
+                };
+                let qual = if a.qual.is_some() && !a.seq.is_empty() {
+                    qual(a)
+                } else {
+                    "*".to_string()
+                };
+                let mut line = if unmapped {
+                    format!("{query}\t4\t*\t0\t0\t*\t*\t0\t0\t{seq}\t{qual}")
+                } else {
+                    format!(
+                        "{query}\t0\t{target}\t1\t255\t{cigar_string}\t*\t0\t0\t{seq}\t{qual}\tAS:i:{score}"
+                    )
+                };
+                if let (Some(cigar), false) = (cigar, unmapped) {
+                    line += &format!("\tNM:i:{}", cigar_counts(cigar).1);
+                }
+                line += &self.tags(time, stats);
+                line
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn writer(format: Format) -> Writer {
+        Writer {
+            format,
+            cigar_style: CigarStyle::Extended,
+            qualities: false,
+            time: false,
+            stats: false,
+        }
+    }
+
+    /// Pairs the aligner gave up on are written as unmapped records.
+    #[test]
+    fn unmapped() {
+        let pair = Pair {
+            key: None,
+            names: ("q".into(), "t".into()),
+            a: io::Record::new(b"ACGT".to_vec()),
+            b: io::Record::new(b"AGT".to_vec()),
+        };
+        let aln = Alignment {
+            pair: &pair,
+            cost: Cost::MAX,
+            ends: None,
+            cigar: None,
+            time: 0.,
+            stats: &AlignerStats::default(),
+        };
+        assert_eq!(
+            writer(Format::Sam).line(&aln),
+            "q\t4\t*\t0\t0\t*\t*\t0\t0\tACGT\t*"
+        );
+        assert_eq!(
+            writer(Format::Paf).line(&aln),
+            "q\t4\t0\t0\t*\t*\t0\t0\t0\t0\t0\t0"
+        );
+    }
+}
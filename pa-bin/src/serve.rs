@@ -0,0 +1,214 @@
+//! `pa-bin serve`: align pairs sent as json lines, keeping built aligners alive between requests.
+//!
+//! Each request is a json object on a single line:
+//! ```json
+//! {"id": 1, "a": "ACGT", "b": "AGT", "aligner": "edlib", "costs": {"sub": 1, "open": 0, "extend": 1}, "trace": true}
+//! ```
+//! - `aligner` is the name of an aligner with default parameters, or `AlignerParams` as json;
+//! - `costs` are explicit costs or the name of a `CostPreset`, defaulting to unit costs;
+//! - `trace` defaults to true;
+//! - `id` is optional and returned unchanged.
+//!
+//! Each request is answered by one line, in order:
+//! `{"id": 1, "cost": 1, "cigar": "1=1D2="}`, or `{"id": 1, "score": .., "a_end": .., "b_end": .., "cigar": ..}`
+//! for extension aligners, or `{"id": 1, "error": "..."}` for invalid requests and aligner panics.
+//! Aligners are built on first use and cached per aligner parameters, costs, and `trace`.
+//! The least recently used aligner is dropped when more than [`MAX_ALIGNERS`] are cached.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+use clap::{Args, ValueEnum};
+use pa_wrapper::{Aligner, AlignerParams, AnyAligner, CostPreset, Costs, CostsOrPreset};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Listen on this Unix socket instead of reading stdin and writing stdout.
+    ///
+    /// Connections are handled one at a time, and share the cached aligners: a client that
+    /// keeps its connection open makes later clients wait until it closes. A stale socket file
+    /// from a server that is no longer running is replaced, and the socket is removed on Ctrl-C.
+    #[clap(long, value_name = "PATH")]
+    socket: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    a: String,
+    b: String,
+    aligner: Value,
+    #[serde(default = "unit_costs")]
+    costs: CostsOrPreset,
+    #[serde(default = "default_trace")]
+    trace: bool,
+}
+
+fn unit_costs() -> CostsOrPreset {
+    CostsOrPreset::Preset(CostPreset::EditDistance)
+}
+
+fn default_trace() -> bool {
+    true
+}
+
+/// The maximum number of cached aligners, which may each hold large buffers.
+const MAX_ALIGNERS: usize = 16;
+
+type AlignerKey = (String, Costs, bool);
+
+/// Built aligners, keyed by their json parameters, costs, and whether they trace.
+#[derive(Default)]
+struct Server {
+    /// Each aligner with the number of the request that last used it.
+    aligners: HashMap<AlignerKey, (AnyAligner, usize)>,
+    requests: usize,
+}
+
+impl Server {
+    /// The response line for a request line.
+    fn respond(&mut self, line: &str) -> Value {
+        let request: Request = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(err) => return json!({ "id": null, "error": format!("Invalid request: {err}") }),
+        };
+        let id = request.id.clone();
+        match self.align(request) {
+            Ok(mut response) => {
+                response["id"] = id;
+                response
+            }
+            Err(err) => json!({ "id": id, "error": err }),
+        }
+    }
+
+    fn align(&mut self, request: Request) -> Result<Value, String> {
+        let params: AlignerParams = match &request.aligner {
+            Value::String(name) => Aligner::from_str(name, true)?.default_params(),
+            params => serde_json::from_value(params.clone())
+                .map_err(|err| format!("Invalid aligner parameters: {err}"))?,
+        };
+        let costs: Costs = request.costs.into();
+        let key = (
+            serde_json::to_string(&params).unwrap(),
+            costs,
+            request.trace,
+        );
+        self.requests += 1;
+        if !self.aligners.contains_key(&key) {
+            let aligner = params
+                .try_build_any_aligner(costs, request.trace, 0)
+                .map_err(|err| format!("Bad aligner parameters: {err}"))?;
+            if self.aligners.len() >= MAX_ALIGNERS {
+                let oldest = self
+                    .aligners
+                    .iter()
+                    .min_by_key(|(_, (_, last_used))| *last_used)
+                    .map(|(key, _)| key.clone())
+                    .unwrap();
+                self.aligners.remove(&oldest);
+            }
+            self.aligners.insert(key.clone(), (aligner, 0));
+        }
+        let (aligner, last_used) = self.aligners.get_mut(&key).unwrap();
+        *last_used = self.requests;
+        let (a, b) = (request.a.as_bytes(), request.b.as_bytes());
+        // A panicking aligner may be left in an invalid state, so it is dropped.
+        panic::catch_unwind(AssertUnwindSafe(|| align(aligner, a, b))).map_err(|err| {
+            self.aligners.remove(&key);
+            let message = err
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| err.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            format!("Aligner panicked: {message}")
+        })
+    }
+
+    /// Answer requests until the end of `input`, flushing after each response.
+    fn run(&mut self, input: impl BufRead, mut output: impl Write) -> std::io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            writeln!(output, "{}", self.respond(&line))?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// The response to aligning `a` and `b`, without its `id`.
+fn align(aligner: &mut AnyAligner, a: &[u8], b: &[u8]) -> Value {
+    match aligner {
+        AnyAligner::Extension(aligner) => {
+            let (extension, cigar, _stats) = aligner.align_extension(a, b);
+            json!({
+                "score": extension.score,
+                "a_end": extension.a_end,
+                "b_end": extension.b_end,
+                "cigar": cigar.map(|c| c.to_string()),
+            })
+        }
+        AnyAligner::Global(aligner) => {
+            let (cost, cigar, _stats) = aligner.align(a, b);
+            json!({ "cost": cost, "cigar": cigar.map(|c| c.to_string()) })
+        }
+    }
+}
+
+/// Remove the socket file at `path` when no server is listening on it anymore.
+fn remove_stale_socket(path: &Path) -> std::io::Result<()> {
+    let is_socket = fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket());
+    if is_socket && UnixStream::connect(path).is_err() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+pub fn serve(args: ServeArgs) {
+    let mut server = Server::default();
+    let Some(socket) = args.socket else {
+        if let Err(err) = server.run(std::io::stdin().lock(), std::io::stdout().lock()) {
+            eprintln!("{err}");
+            exit(1);
+        }
+        return;
+    };
+    let listener = remove_stale_socket(&socket)
+        .and_then(|()| UnixListener::bind(&socket))
+        .unwrap_or_else(|err| {
+            eprintln!("Failed to listen on {}: {err}", socket.display());
+            exit(1);
+        });
+    {
+        let socket = socket.clone();
+        ctrlc::set_handler(move || {
+            let _ = fs::remove_file(&socket);
+            exit(0);
+        })
+        .expect("Error setting Ctrl-C handler");
+    }
+    eprintln!("Listening on {}", socket.display());
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| {
+            let input = BufReader::new(stream.try_clone()?);
+            server.run(input, stream)
+        });
+        // A failing connection does not stop the server.
+        if let Err(err) = result {
+            eprintln!("Connection failed: {err}");
+        }
+    }
+}
@@ -0,0 +1,25 @@
+//! Argument and input errors of the `pa-bin` binary, and their exit codes.
+
+use std::process::{Command, Output};
+
+/// Run `pa-bin` with `args` and without stdin.
+fn pa_bin(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_pa-bin"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn paf_requires_cigar() {
+    let output = pa_bin(&["--aligner", "dp", "--format", "paf", "--cost-only", "-"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--cost-only"));
+}
+
+#[test]
+fn missing_input() {
+    let output = pa_bin(&["--aligner", "dp", "does-not-exist.seq"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("does-not-exist.seq"));
+}
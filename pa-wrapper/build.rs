@@ -0,0 +1,62 @@
+//! Exposes the resolved version and git revision of each aligner dependency as
+//! `PA_DEP_VERSION_<CRATE>` and `PA_DEP_REV_<CRATE>` environment variables,
+//! so that `AlignerInfo` can report exactly which backend was linked in.
+//!
+//! The versions are read from the `Cargo.lock` of the enclosing workspace.
+//! When no lock file is found, the variables are simply not set.
+
+use std::path::PathBuf;
+
+/// The dependencies to report.
+const DEPENDENCIES: &[&str] = &[
+    "astarpa",
+    "astarpa2",
+    "block-aligner",
+    "edlib_rs",
+    "ksw2-sys",
+    "parasailors",
+    "rust-wfa2",
+    "triple_accel",
+];
+
+fn main() {
+    let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let Some(lock_path) = manifest_dir
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.is_file())
+    else {
+        return;
+    };
+    println!("cargo:rerun-if-changed={}", lock_path.display());
+    let Ok(lock) = std::fs::read_to_string(&lock_path) else {
+        return;
+    };
+
+    // A minimal parser for the `[[package]]` tables in `Cargo.lock`.
+    for package in lock.split("[[package]]").skip(1) {
+        let field = |key: &str| {
+            package.lines().find_map(|line| {
+                let (k, v) = line.split_once(" = ")?;
+                (k.trim() == key).then(|| v.trim().trim_matches('"').to_string())
+            })
+        };
+        let Some(name) = field("name") else {
+            continue;
+        };
+        if !DEPENDENCIES.contains(&name.as_str()) {
+            continue;
+        }
+        let env_name = name.to_uppercase().replace('-', "_");
+        if let Some(version) = field("version") {
+            println!("cargo:rustc-env=PA_DEP_VERSION_{env_name}={version}");
+        }
+        // Git sources look like `git+https://github.com/user/repo?branch=x#<revision>`.
+        if let Some(rev) = field("source")
+            .filter(|source| source.starts_with("git+"))
+            .and_then(|source| Some(source.split_once('#')?.1.to_string()))
+        {
+            println!("cargo:rustc-env=PA_DEP_REV_{env_name}={rev}");
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Count and enumerate co-optimal alignments, i.e. all alignments with minimal cost.
+//!
+//! This uses the quadratic DP of `wrappers::dp`, and is only meant for small inputs.
+//! Two alignments are different when their sequences of operations differ.
+
+use crate::wrappers::dp::{op, Dp, State};
+use crate::*;
+
+/// The minimal cost of aligning `a` and `b`, and the number of alignments with this cost.
+///
+/// The count saturates at `u64::MAX`.
+pub fn count_co_optimal(costs: impl Into<Costs>, a: Seq, b: Seq) -> (Cost, u64) {
+    let dp_aligner = Dp::new(costs.into(), true);
+    let dp = dp_aligner.matrix(a, b);
+    let w = b.len() + 1;
+    let idx = |(i, j, state): (usize, usize, State)| 3 * (i * w + j) + state as usize;
+
+    // The number of optimal paths from the origin to each state of each cell.
+    let mut counts = vec![0u64; 3 * dp.len()];
+    counts[idx((0, 0, State::M))] = 1;
+    for i in 0..=a.len() {
+        for j in 0..=b.len() {
+            for state in [State::M, State::Ins, State::Del] {
+                let u = (i, j, state);
+                for p in dp_aligner.predecessors(&dp, a, b, u) {
+                    counts[idx(u)] = counts[idx(u)].saturating_add(counts[idx(p)]);
+                }
+            }
+        }
+    }
+
+    let end = &dp[a.len() * w + b.len()];
+    let count = end
+        .optimal_states()
+        .map(|state| counts[idx((a.len(), b.len(), state))])
+        .fold(0u64, |acc, c| acc.saturating_add(c));
+    (end.min(), count)
+}
+
+/// The minimal cost of aligning `a` and `b`, and up to `n` distinct alignments with this cost.
+pub fn co_optimal_alignments(
+    costs: impl Into<Costs>,
+    a: Seq,
+    b: Seq,
+    n: usize,
+) -> (Cost, Vec<Cigar>) {
+    let dp_aligner = Dp::new(costs.into(), true);
+    let dp = dp_aligner.matrix(a, b);
+    let end = &dp[a.len() * (b.len() + 1) + b.len()];
+
+    // Depth-first search over optimal predecessors, from the end back to the origin.
+    // Each partial path can be completed to an optimal alignment, so there are no dead ends.
+    let mut cigars = vec![];
+    let mut stack: Vec<((usize, usize, State), Vec<CigarOp>)> = end
+        .optimal_states()
+        .map(|state| ((a.len(), b.len(), state), vec![]))
+        .collect();
+    stack.reverse();
+    while cigars.len() < n {
+        let Some((u, mut ops)) = stack.pop() else {
+            break;
+        };
+        if (u.0, u.1) == (0, 0) {
+            ops.reverse();
+            cigars.push(Cigar::from_ops(ops.into_iter()));
+            continue;
+        }
+        ops.push(op(a, b, u));
+        let mut preds = dp_aligner.predecessors(&dp, a, b, u);
+        // Explore the first predecessor first, reusing `ops` for it.
+        let first = preds.remove(0);
+        for p in preds.into_iter().rev() {
+            stack.push((p, ops.clone()));
+        }
+        stack.push((first, ops));
+    }
+    (end.min(), cigars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The co-optimal alignments of `a` and `b`, as sorted cigar strings.
+    fn alignments(costs: CostModel, a: Seq, b: Seq) -> (Cost, Vec<String>) {
+        let (cost, cigars) = co_optimal_alignments(costs, a, b, usize::MAX);
+        let mut cigars: Vec<_> = cigars.iter().map(|c| c.to_string()).collect();
+        cigars.sort();
+        (cost, cigars)
+    }
+
+    #[test]
+    fn unique() {
+        let unit = CostModel::unit();
+        assert_eq!(count_co_optimal(unit, b"", b""), (0, 1));
+        assert_eq!(count_co_optimal(unit, b"ACGT", b"ACGT"), (0, 1));
+        // A substitution is cheaper than a deletion and an insertion.
+        assert_eq!(count_co_optimal(unit, b"ACGT", b"AGGT"), (1, 1));
+        assert_eq!(
+            alignments(unit, b"ACGT", b"AGGT"),
+            (1, vec!["1=1X2=".into()])
+        );
+    }
+
+    #[test]
+    fn ties() {
+        let unit = CostModel::unit();
+        // Either A can be deleted.
+        assert_eq!(count_co_optimal(unit, b"AA", b"A"), (1, 2));
+        assert_eq!(
+            alignments(unit, b"AA", b"A"),
+            (1, vec!["1=1D".into(), "1D1=".into()])
+        );
+        // Any one of the three A's can be kept.
+        assert_eq!(count_co_optimal(unit, b"AAA", b"A"), (2, 3));
+        // With affine costs, keeping the middle A opens two gaps.
+        let affine = CostModel {
+            sub: 1,
+            open: 2,
+            extend: 1,
+        };
+        assert_eq!(count_co_optimal(affine, b"AAA", b"A"), (2 + 2, 2));
+        assert_eq!(
+            alignments(affine, b"AAA", b"A"),
+            (2 + 2, vec!["1=2D".into(), "2D1=".into()])
+        );
+    }
+}
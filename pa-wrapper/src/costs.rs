@@ -0,0 +1,508 @@
+//! A `CostModel` with optionally separate gap costs for insertions and deletions,
+//! and an optional match bonus.
+
+use crate::*;
+
+/// An affine cost model, where insertions and deletions may have different gap costs.
+///
+/// Insertions are characters of `b` that are not in `a`, deletions are characters
+/// of `a` that are not in `b`.
+/// A gap of length `l` costs `open + l * extend`.
+///
+/// With a `match_bonus`, the *score* of an alignment is `match_bonus * #matches - cost`,
+/// which is to be maximized. For global alignment, this is equivalent to
+/// minimizing the cost under the cost model returned by `without_match_bonus`
+/// (Eizenga & Paten, 2022), which is what aligners receive.
+///
+/// When none of the `ins_*`, `del_*` and `match_bonus` fields are set, this is equivalent to a
+/// `CostModel`, and it (de)serializes in the same way.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, clap::Args)]
+#[serde(deny_unknown_fields)]
+pub struct Costs {
+    /// Substitution cost, (> 0)
+    #[arg(long, default_value_t = 1, value_name = "COST")]
+    pub sub: Cost,
+    /// Gap open cost (>= 0)
+    #[arg(long, default_value_t = 0, value_name = "COST")]
+    pub open: Cost,
+    /// Gap extend cost (> 0)
+    #[arg(long, default_value_t = 1, value_name = "COST")]
+    pub extend: Cost,
+
+    /// Gap open cost for insertions. Defaults to `open`.
+    #[arg(long, value_name = "COST")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ins_open: Option<Cost>,
+    /// Gap extend cost for insertions. Defaults to `extend`.
+    #[arg(long, value_name = "COST")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ins_extend: Option<Cost>,
+    /// Gap open cost for deletions. Defaults to `open`.
+    #[arg(long, value_name = "COST")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub del_open: Option<Cost>,
+    /// Gap extend cost for deletions. Defaults to `extend`.
+    #[arg(long, value_name = "COST")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub del_extend: Option<Cost>,
+
+    /// Bonus for each match (>= 0). Aligners run on an equivalent cost model.
+    #[arg(long, value_name = "BONUS")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub match_bonus: Option<Cost>,
+}
+
+/// Named cost models that mimic common tools.
+///
+/// Presets with a match bonus keep it, so that scores are comparable to the tool.
+/// As for all `Costs`, aligners run on the equivalent costs given by `Costs::without_match_bonus`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum CostPreset {
+    /// Edit distance: `sub: 1, open: 0, extend: 1`.
+    EditDistance,
+    /// The affine costs used throughout `evals/experiments`: `sub: 1, open: 1, extend: 1`.
+    Affine,
+    /// minimap2 `-x map-ont` (`-A2 -B4 -O4 -E2`, using its first gap cost):
+    /// `sub: 4, open: 4, extend: 2, match_bonus: 2`.
+    Minimap2MapOnt,
+    /// BWA-MEM defaults (`-A1 -B4 -O6 -E1`): `sub: 4, open: 6, extend: 1, match_bonus: 1`.
+    BwaMem,
+}
+
+impl CostPreset {
+    pub fn costs(self) -> Costs {
+        let (sub, open, extend, match_bonus) = match self {
+            CostPreset::EditDistance => (1, 0, 1, None),
+            CostPreset::Affine => (1, 1, 1, None),
+            CostPreset::Minimap2MapOnt => (4, 4, 2, Some(2)),
+            CostPreset::BwaMem => (4, 6, 1, Some(1)),
+        };
+        Costs {
+            match_bonus,
+            ..CostModel { sub, open, extend }.into()
+        }
+    }
+}
+
+/// Costs given explicitly, or by the name of a [`CostPreset`], as in experiment yaml:
+/// `costs: [bwa-mem, { sub: 1, open: 0, extend: 1 }]`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum CostsOrPreset {
+    Preset(CostPreset),
+    Costs(Costs),
+}
+
+impl From<CostsOrPreset> for Costs {
+    fn from(costs: CostsOrPreset) -> Self {
+        match costs {
+            CostsOrPreset::Preset(preset) => preset.costs(),
+            CostsOrPreset::Costs(costs) => costs,
+        }
+    }
+}
+
+impl From<CostModel> for Costs {
+    fn from(cm: CostModel) -> Self {
+        Self {
+            sub: cm.sub,
+            open: cm.open,
+            extend: cm.extend,
+            ins_open: None,
+            ins_extend: None,
+            del_open: None,
+            del_extend: None,
+            match_bonus: None,
+        }
+    }
+}
+
+/// A symmetric `CostModel` that is equivalent to asymmetric `Costs` for global alignment,
+/// as given by `Costs::to_symmetric`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymmetricCosts {
+    /// The cost model to align with.
+    pub cm: CostModel,
+    /// The factor by which `cm` scales costs.
+    factor: Cost,
+    /// How much more each deleted character costs than `cm.extend`, scaled by `factor`.
+    skew: Cost,
+}
+
+impl From<CostModel> for SymmetricCosts {
+    fn from(cm: CostModel) -> Self {
+        Self {
+            cm,
+            factor: 1,
+            skew: 0,
+        }
+    }
+}
+
+impl SymmetricCosts {
+    /// Convert the cost under `cm` of a global alignment of sequences of lengths
+    /// `a_len` and `b_len` back to the original costs (without match bonus).
+    pub fn cost(&self, cost: Cost, a_len: usize, b_len: usize) -> Cost {
+        (cost + self.skew * (a_len as Cost - b_len as Cost)) / self.factor
+    }
+}
+
+impl Costs {
+    /// The `(open, extend)` costs of insertions.
+    pub fn ins(&self) -> (Cost, Cost) {
+        (
+            self.ins_open.unwrap_or(self.open),
+            self.ins_extend.unwrap_or(self.extend),
+        )
+    }
+
+    /// The `(open, extend)` costs of deletions.
+    pub fn del(&self) -> (Cost, Cost) {
+        (
+            self.del_open.unwrap_or(self.open),
+            self.del_extend.unwrap_or(self.extend),
+        )
+    }
+
+    /// Whether insertions and deletions have the same costs.
+    pub fn is_symmetric(&self) -> bool {
+        self.ins() == self.del()
+    }
+
+    /// The factor by which `without_match_bonus` scales costs.
+    /// Odd match bonuses require doubling all costs to keep them integral.
+    pub fn match_bonus_factor(&self) -> Cost {
+        match self.match_bonus {
+            Some(bonus) if bonus % 2 != 0 => 2,
+            _ => 1,
+        }
+    }
+
+    /// The equivalent costs without match bonus, for global alignment.
+    ///
+    /// Each alignment of `a` and `b` has `2 * #matches = |a| + |b| - 2 * #subs - #gap_chars`,
+    /// so that `f * score = f * bonus * (|a| + |b|) / 2 - cost'`, where `cost'` uses
+    /// - `sub' = f * (sub + bonus)`,
+    /// - `open' = f * open`,
+    /// - `extend' = f * (extend + bonus / 2)`,
+    ///
+    /// and `f` is `match_bonus_factor`.
+    /// See Eizenga & Paten (2022), https://doi.org/10.1101/2022.01.12.476087.
+    pub fn without_match_bonus(&self) -> Costs {
+        let Some(bonus) = self.match_bonus else {
+            return *self;
+        };
+        let f = self.match_bonus_factor();
+        let (ins_open, ins_extend) = self.ins();
+        let (del_open, del_extend) = self.del();
+        let open = f * self.open;
+        let extend = f * self.extend + f * bonus / 2;
+        let ins_open = f * ins_open;
+        let ins_extend = f * ins_extend + f * bonus / 2;
+        let del_open = f * del_open;
+        let del_extend = f * del_extend + f * bonus / 2;
+        Costs {
+            sub: f * (self.sub + bonus),
+            open,
+            extend,
+            ins_open: (ins_open != open).then_some(ins_open),
+            ins_extend: (ins_extend != extend).then_some(ins_extend),
+            del_open: (del_open != open).then_some(del_open),
+            del_extend: (del_extend != extend).then_some(del_extend),
+            match_bonus: None,
+        }
+    }
+
+    /// Convert a cost under `without_match_bonus` back to a score for sequences
+    /// of lengths `a_len` and `b_len`.
+    /// Without match bonus, this is simply the negated cost.
+    pub fn score(&self, cost: Cost, a_len: usize, b_len: usize) -> Cost {
+        let bonus = self.match_bonus.unwrap_or(0);
+        let f = self.match_bonus_factor();
+        (f * bonus * (a_len + b_len) as Cost / 2 - cost) / f
+    }
+
+    /// The equivalent `CostModel` without match bonus, if insertions and deletions have the same costs.
+    pub fn to_cost_model(&self) -> Option<CostModel> {
+        let costs = self.without_match_bonus();
+        costs.is_symmetric().then_some(CostModel {
+            sub: costs.sub,
+            open: costs.ins().0,
+            extend: costs.ins().1,
+        })
+    }
+
+    /// An equivalent symmetric cost model without match bonus for global alignment,
+    /// if insertions and deletions have the same open cost.
+    ///
+    /// Each alignment of `a` and `b` has `#deleted - #inserted = |a| - |b|` gap characters,
+    /// so with `e = (ins_extend + del_extend) / 2`, the gap characters cost
+    /// `e * (#deleted + #inserted) + (del_extend - e) * (|a| - |b|)`.
+    /// The costs are doubled when `e` is not integral.
+    pub fn to_symmetric(&self) -> Option<SymmetricCosts> {
+        let costs = self.without_match_bonus();
+        let (ins_open, ins_extend) = costs.ins();
+        let (del_open, del_extend) = costs.del();
+        if ins_open != del_open {
+            return None;
+        }
+        let factor = if (ins_extend + del_extend) % 2 != 0 {
+            2
+        } else {
+            1
+        };
+        Some(SymmetricCosts {
+            cm: CostModel {
+                sub: factor * costs.sub,
+                open: factor * ins_open,
+                extend: factor * (ins_extend + del_extend) / 2,
+            },
+            factor,
+            skew: factor * (del_extend - ins_extend) / 2,
+        })
+    }
+
+    /// Check that `cigar` is a valid alignment of `a` and `b`, and return its cost
+    /// under `without_match_bonus`.
+    ///
+    /// Consecutive cigar elements of the same gap type are counted as a single gap.
+    pub fn verify(&self, cigar: &Cigar, a: Seq, b: Seq) -> Result<Cost, String> {
+        let costs = self.without_match_bonus();
+        let (ins_open, ins_extend) = costs.ins();
+        let (del_open, del_extend) = costs.del();
+        let mut i = 0;
+        let mut j = 0;
+        let mut cost = 0;
+        let mut in_ins = false;
+        let mut in_del = false;
+        for CigarElem { op, cnt } in &cigar.ops {
+            let cnt = *cnt as usize;
+            match op {
+                CigarOp::Match | CigarOp::Sub => {
+                    if i + cnt > a.len() || j + cnt > b.len() {
+                        return Err(format!(
+                            "Cigar runs past the end of the sequences at ({i}, {j})."
+                        ));
+                    }
+                    let is_match = matches!(op, CigarOp::Match);
+                    if let Some(k) = (0..cnt).find(|&k| (a[i + k] == b[j + k]) != is_match) {
+                        return Err(format!(
+                            "Cigar has a {} at ({}, {}) between '{}' and '{}'.",
+                            if is_match { "match" } else { "substitution" },
+                            i + k,
+                            j + k,
+                            a[i + k] as char,
+                            b[j + k] as char,
+                        ));
+                    }
+                    if !is_match {
+                        cost += cnt as Cost * costs.sub;
+                    }
+                    i += cnt;
+                    j += cnt;
+                    in_ins = false;
+                    in_del = false;
+                }
+                CigarOp::Ins => {
+                    if j + cnt > b.len() {
+                        return Err(format!("Insertion runs past the end of b at ({i}, {j})."));
+                    }
+                    if !in_ins {
+                        cost += ins_open;
+                    }
+                    cost += cnt as Cost * ins_extend;
+                    j += cnt;
+                    in_ins = true;
+                    in_del = false;
+                }
+                CigarOp::Del => {
+                    if i + cnt > a.len() {
+                        return Err(format!("Deletion runs past the end of a at ({i}, {j})."));
+                    }
+                    if !in_del {
+                        cost += del_open;
+                    }
+                    cost += cnt as Cost * del_extend;
+                    i += cnt;
+                    in_ins = false;
+                    in_del = true;
+                }
+            }
+        }
+        if (i, j) != (a.len(), b.len()) {
+            return Err(format!(
+                "Cigar ends at ({i}, {j}) instead of ({}, {}).",
+                a.len(),
+                b.len()
+            ));
+        }
+        Ok(cost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use CigarOp::*;
+
+    fn cigar(ops: &[(CigarOp, I)]) -> Cigar {
+        Cigar {
+            ops: ops.iter().map(|&(op, cnt)| CigarElem { op, cnt }).collect(),
+        }
+    }
+
+    fn affine(sub: Cost, open: Cost, extend: Cost) -> Costs {
+        CostModel { sub, open, extend }.into()
+    }
+
+    /// Insertions cost `1 + l`, deletions cost `3 + 2 * l`.
+    fn asymmetric() -> Costs {
+        Costs {
+            ins_open: Some(1),
+            ins_extend: Some(1),
+            del_open: Some(3),
+            del_extend: Some(2),
+            ..affine(2, 2, 1)
+        }
+    }
+
+    #[test]
+    fn verify_unit() {
+        let unit = affine(1, 0, 1);
+        let c = cigar(&[(Match, 1), (Sub, 1), (Match, 2)]);
+        assert_eq!(unit.verify(&c, b"ACGT", b"AGGT"), Ok(1));
+        let c = cigar(&[(Match, 1), (Del, 1), (Match, 2)]);
+        assert_eq!(unit.verify(&c, b"ACGT", b"AGT"), Ok(1));
+    }
+
+    #[test]
+    fn verify_affine() {
+        let costs = affine(1, 2, 1);
+        // Inserting GGG costs 2 + 3.
+        let c = cigar(&[(Match, 2), (Ins, 3), (Match, 2)]);
+        assert_eq!(costs.verify(&c, b"AACC", b"AAGGGCC"), Ok(5));
+        // Consecutive elements of the same type are a single gap.
+        let c = cigar(&[(Match, 2), (Ins, 1), (Ins, 2), (Match, 2)]);
+        assert_eq!(costs.verify(&c, b"AACC", b"AAGGGCC"), Ok(5));
+        // An insertion followed by a deletion opens two gaps.
+        let c = cigar(&[(Match, 1), (Ins, 1), (Del, 1)]);
+        assert_eq!(costs.verify(&c, b"AC", b"AG"), Ok(6));
+    }
+
+    #[test]
+    fn verify_asymmetric() {
+        let costs = asymmetric();
+        let c = cigar(&[(Match, 2), (Del, 2), (Match, 2)]);
+        assert_eq!(costs.verify(&c, b"AAGGCC", b"AACC"), Ok(3 + 2 * 2));
+        let c = cigar(&[(Match, 2), (Ins, 2), (Match, 2)]);
+        assert_eq!(costs.verify(&c, b"AACC", b"AAGGCC"), Ok(1 + 2));
+    }
+
+    #[test]
+    fn verify_rejects_invalid_cigars() {
+        let unit = affine(1, 0, 1);
+        // A match of different characters.
+        assert!(unit.verify(&cigar(&[(Match, 2)]), b"AC", b"AG").is_err());
+        // A substitution of equal characters.
+        assert!(unit
+            .verify(&cigar(&[(Sub, 1), (Match, 1)]), b"AC", b"AC")
+            .is_err());
+        // Not all of `a` is aligned.
+        assert!(unit.verify(&cigar(&[(Match, 1)]), b"AC", b"A").is_err());
+        // Running past the end of `b`.
+        assert!(unit
+            .verify(&cigar(&[(Match, 1), (Ins, 1)]), b"A", b"A")
+            .is_err());
+    }
+
+    #[test]
+    fn without_match_bonus_even() {
+        // sub: 4, open: 4, extend: 2, match_bonus: 2.
+        let costs = CostPreset::Minimap2MapOnt.costs();
+        assert_eq!(costs.match_bonus_factor(), 1);
+        assert_eq!(costs.without_match_bonus(), affine(6, 4, 3));
+        // 3 matches and a deletion score 3 * 2 - (4 + 2) = 0.
+        let c = cigar(&[(Match, 1), (Del, 1), (Match, 2)]);
+        let cost = costs.verify(&c, b"ACGT", b"AGT").unwrap();
+        assert_eq!(cost, 4 + 3);
+        assert_eq!(costs.score(cost, 4, 3), 0);
+    }
+
+    #[test]
+    fn without_match_bonus_odd() {
+        // sub: 4, open: 6, extend: 1, match_bonus: 1.
+        let costs = CostPreset::BwaMem.costs();
+        assert_eq!(costs.match_bonus_factor(), 2);
+        assert_eq!(costs.without_match_bonus(), affine(10, 12, 3));
+        // 3 matches and a substitution score 3 - 4 = -1.
+        let c = cigar(&[(Match, 1), (Sub, 1), (Match, 2)]);
+        let cost = costs.verify(&c, b"ACGT", b"AGGT").unwrap();
+        assert_eq!(cost, 10);
+        assert_eq!(costs.score(cost, 4, 4), -1);
+        // 3 matches and a deletion score 3 - (6 + 1) = -4.
+        let c = cigar(&[(Match, 1), (Del, 1), (Match, 2)]);
+        let cost = costs.verify(&c, b"ACGT", b"AGT").unwrap();
+        assert_eq!(cost, 12 + 3);
+        assert_eq!(costs.score(cost, 4, 3), -4);
+    }
+
+    #[test]
+    fn without_match_bonus_asymmetric() {
+        let costs = Costs {
+            match_bonus: Some(1),
+            ..asymmetric()
+        };
+        assert_eq!(
+            costs.without_match_bonus(),
+            Costs {
+                ins_open: Some(2),
+                ins_extend: None,
+                del_open: Some(6),
+                del_extend: Some(5),
+                ..affine(6, 4, 3)
+            }
+        );
+        // 4 matches and a deletion score 4 - (3 + 2 * 2) = -3.
+        let c = cigar(&[(Match, 2), (Del, 2), (Match, 2)]);
+        let cost = costs.verify(&c, b"AAGGCC", b"AACC").unwrap();
+        assert_eq!(cost, 6 + 2 * 5);
+        assert_eq!(costs.score(cost, 6, 4), -3);
+    }
+
+    #[test]
+    fn to_symmetric() {
+        assert_eq!(
+            affine(1, 2, 1).to_symmetric(),
+            Some(
+                CostModel {
+                    sub: 1,
+                    open: 2,
+                    extend: 1
+                }
+                .into()
+            )
+        );
+        // Different open costs can not be made symmetric.
+        assert_eq!(asymmetric().to_symmetric(), None);
+
+        // Insertions cost `2 + l`, deletions cost `2 + 2 * l`.
+        let costs = Costs {
+            del_extend: Some(2),
+            ..affine(1, 2, 1)
+        };
+        let symmetric = costs.to_symmetric().unwrap();
+        assert_eq!(
+            symmetric.cm,
+            CostModel {
+                sub: 2,
+                open: 4,
+                extend: 3
+            }
+        );
+        // Deleting GG.
+        assert_eq!(symmetric.cost(4 + 2 * 3, 6, 4), 2 + 2 * 2);
+        // Inserting GG.
+        assert_eq!(symmetric.cost(4 + 2 * 3, 4, 6), 2 + 2);
+    }
+}
@@ -0,0 +1,804 @@
+//! Reading and writing sequence pairs in `.seq`, `.txt`, FASTA, and FASTQ files,
+//! optionally compressed with gzip (including bgzip) or zstd.
+//!
+//! - `.seq` files alternate lines starting with `>` (first sequence of a pair) and `<` (second sequence).
+//!   A pair may be preceded by a `#<id>` line that names it.
+//! - `.txt` files alternate plain lines.
+//! - In FASTA (`.fa`, `.fasta`, `.fna`) and FASTQ (`.fq`, `.fastq`) files, consecutive records form pairs.
+//!
+//! The ID of a pair is the `#<id>` header in `.seq` files, and the name of its first
+//! record in FASTA and FASTQ files. See [`pair_id`].
+//!
+//! Compression is detected from the magic bytes at the start of the file, so gzip, bgzip,
+//! and zstd files are read transparently. The format is detected from the extension
+//! (ignoring a trailing `.gz`, `.bgz`, or `.zst`) or, when that is unknown, from the contents.
+//! The path `-` reads from stdin.
+//!
+//! Malformed input results in an [`Error`] with the file and line number.
+//! Reading can continue after a malformed record or pair, skipping it, but not after a read error.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::*;
+
+/// The file format of a sequence file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Seq,
+    Txt,
+    Fasta,
+    Fastq,
+}
+
+/// The compression of a sequence file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// Gzip, including multi-member files such as bgzip.
+    Gzip,
+    Zstd,
+}
+
+impl Format {
+    /// The format and compression of `path` based on its extension, e.g. `reads.fastq.gz`.
+    pub fn from_path(path: &Path) -> Option<(Format, Compression)> {
+        let name = path.file_name()?.to_str()?;
+        let (name, compression) = if let Some(name) = name.strip_suffix(".zst") {
+            (name, Compression::Zstd)
+        } else if let Some(name) = name
+            .strip_suffix(".gz")
+            .or_else(|| name.strip_suffix(".bgz"))
+        {
+            (name, Compression::Gzip)
+        } else {
+            (name, Compression::None)
+        };
+        let format = match name.rsplit_once('.')?.1 {
+            "seq" => Format::Seq,
+            "txt" => Format::Txt,
+            "fa" | "fasta" | "fna" => Format::Fasta,
+            "fq" | "fastq" => Format::Fastq,
+            _ => return None,
+        };
+        Some((format, compression))
+    }
+
+    /// The canonical extension of this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Seq => "seq",
+            Format::Txt => "txt",
+            Format::Fasta => "fa",
+            Format::Fastq => "fq",
+        }
+    }
+}
+
+/// A single sequence, with its name and qualities when the format has them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Record {
+    /// The record name in FASTA and FASTQ files, and the pair ID in `.seq` files.
+    pub id: Option<String>,
+    pub seq: Sequence,
+    pub qual: Option<Vec<u8>>,
+}
+
+impl Record {
+    pub fn new(seq: Sequence) -> Self {
+        Record {
+            seq,
+            ..Record::default()
+        }
+    }
+}
+
+/// The ID of the pair `(a, b)`, if it has one.
+pub fn pair_id<'a>(a: &'a Record, _b: &Record) -> Option<&'a str> {
+    a.id.as_deref()
+}
+
+/// An I/O error or malformed input, with the file and line it occurred at.
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    /// The 1-based line number, if the error is tied to a line.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn unknown_format(path: &Path) -> Error {
+    Error {
+        path: path.to_path_buf(),
+        line: None,
+        message: "Unknown file format. The extension must be one of .seq, .txt, .fa, .fasta, .fna, .fq, .fastq, optionally followed by .gz, .bgz, or .zst, or the contents must look like one of these.".into(),
+    }
+}
+
+fn io_error(path: &Path) -> impl Fn(io::Error) -> Error + '_ {
+    move |err| Error {
+        path: path.to_path_buf(),
+        line: None,
+        message: err.to_string(),
+    }
+}
+
+impl Compression {
+    /// Detect the compression from the magic bytes at the start of a file.
+    pub fn from_magic(magic: &[u8]) -> Compression {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+impl Format {
+    /// Guess the format from the first (decompressed) lines of a file.
+    ///
+    /// `.txt` is only detected when the first line consists of letters only.
+    pub fn sniff(head: &[u8]) -> Option<Format> {
+        let mut lines = head
+            .split(|&c| c == b'\n')
+            .map(|l| l.strip_suffix(b"\r").unwrap_or(l))
+            .filter(|l| !l.is_empty());
+        let first = lines.next()?;
+        match first[0] {
+            b'@' => Some(Format::Fastq),
+            b'#' => Some(Format::Seq),
+            b'>' => match lines.next() {
+                Some(l) if l.starts_with(b"<") => Some(Format::Seq),
+                _ => Some(Format::Fasta),
+            },
+            _ if first.iter().all(u8::is_ascii_alphabetic) => Some(Format::Txt),
+            _ => None,
+        }
+    }
+}
+
+/// Detect the compression of `path` from its magic bytes.
+pub fn detect_compression(path: &Path) -> Result<Compression, Error> {
+    let mut magic = vec![];
+    File::open(path)
+        .and_then(|f| f.take(4).read_to_end(&mut magic))
+        .map_err(io_error(path))?;
+    Ok(Compression::from_magic(&magic))
+}
+
+/// Detect the format and compression of `path`.
+///
+/// Compression is detected from the magic bytes, and the format from the extension,
+/// falling back to the contents when the extension is unknown.
+pub fn detect(path: &Path) -> Result<(Format, Compression), Error> {
+    let compression = detect_compression(path)?;
+    if let Some((format, _)) = Format::from_path(path) {
+        return Ok((format, compression));
+    }
+
+    // Read the first two non-empty lines, but not too much of binary files.
+    let mut head = vec![];
+    let file = File::open(path).map_err(io_error(path))?;
+    let mut reader = decoder(Box::new(file), compression, path)?.take(1 << 24);
+    let mut lines = 0;
+    while lines < 2 {
+        let start = head.len();
+        if reader
+            .read_until(b'\n', &mut head)
+            .map_err(io_error(path))?
+            == 0
+        {
+            break;
+        }
+        if head[start..].iter().any(|c| !c.is_ascii_whitespace()) {
+            lines += 1;
+        }
+    }
+    let format = Format::sniff(&head).ok_or_else(|| unknown_format(path))?;
+    Ok((format, compression))
+}
+
+/// Wrap `inner` in the given decompression.
+fn decoder(
+    inner: Box<dyn Read>,
+    compression: Compression,
+    path: &Path,
+) -> Result<Box<dyn BufRead>, Error> {
+    const CAPACITY: usize = 1 << 20;
+    Ok(match compression {
+        Compression::None => Box::new(BufReader::with_capacity(CAPACITY, inner)),
+        Compression::Gzip => Box::new(BufReader::with_capacity(
+            CAPACITY,
+            flate2::read::MultiGzDecoder::new(inner),
+        )),
+        Compression::Zstd => Box::new(BufReader::with_capacity(
+            CAPACITY,
+            zstd::Decoder::new(inner).map_err(io_error(path))?,
+        )),
+    })
+}
+
+/// Open `path` for reading, with format and compression detected by [`detect`].
+pub fn open(path: &Path) -> Result<Reader<Box<dyn BufRead>>, Error> {
+    open_as(path, None)
+}
+
+/// Open `path` for reading, with the given format or the detected one.
+///
+/// The path `-` reads from stdin, see [`from_reader`].
+pub fn open_as(path: &Path, format: Option<Format>) -> Result<Reader<Box<dyn BufRead>>, Error> {
+    if path == Path::new("-") {
+        return from_reader(Box::new(io::stdin()), format, path);
+    }
+    let (format, compression) = match format {
+        Some(format) => (format, detect_compression(path)?),
+        None => detect(path)?,
+    };
+    let file = File::open(path).map_err(io_error(path))?;
+    Ok(Reader::new(
+        decoder(Box::new(file), compression, path)?,
+        format,
+        path,
+    ))
+}
+
+/// Read from a stream such as stdin, detecting the compression and, unless given,
+/// the format from its first buffered bytes. `path` is only used in error messages.
+pub fn from_reader(
+    inner: Box<dyn Read>,
+    format: Option<Format>,
+    path: &Path,
+) -> Result<Reader<Box<dyn BufRead>>, Error> {
+    let mut inner = BufReader::new(inner);
+    let compression = Compression::from_magic(inner.fill_buf().map_err(io_error(path))?);
+    let mut inner = decoder(Box::new(inner), compression, path)?;
+    let format = match format {
+        Some(format) => format,
+        None => Format::sniff(inner.fill_buf().map_err(io_error(path))?)
+            .ok_or_else(|| unknown_format(path))?,
+    };
+    Ok(Reader::new(inner, format, path))
+}
+
+/// Read all sequence pairs in `path`.
+pub fn read_pairs(path: &Path) -> Result<Vec<(Record, Record)>, Error> {
+    open(path)?.pairs().collect()
+}
+
+/// A file written by [`create`], optionally compressed.
+pub enum FileWriter {
+    Plain(BufWriter<File>),
+    Gzip(flate2::write::GzEncoder<BufWriter<File>>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl FileWriter {
+    /// Write the end of the compressed stream and flush the file.
+    ///
+    /// Dropping the writer instead loses the errors of doing so.
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            FileWriter::Plain(mut file) => file.flush(),
+            FileWriter::Gzip(encoder) => encoder.finish()?.flush(),
+            FileWriter::Zstd(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for FileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            FileWriter::Plain(file) => file.write(buf),
+            FileWriter::Gzip(encoder) => encoder.write(buf),
+            FileWriter::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            FileWriter::Plain(file) => file.flush(),
+            FileWriter::Gzip(encoder) => encoder.flush(),
+            FileWriter::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Create `path` for writing, with format and compression detected from its extension.
+///
+/// Call [`Writer::finish`] when done, to complete compressed files.
+pub fn create(path: &Path) -> Result<Writer<FileWriter>, Error> {
+    let (format, compression) = Format::from_path(path).ok_or_else(|| unknown_format(path))?;
+    let file = BufWriter::new(File::create(path).map_err(io_error(path))?);
+    let inner = match compression {
+        Compression::None => FileWriter::Plain(file),
+        Compression::Gzip => FileWriter::Gzip(flate2::write::GzEncoder::new(
+            file,
+            flate2::Compression::default(),
+        )),
+        Compression::Zstd => FileWriter::Zstd(zstd::Encoder::new(file, 0).map_err(io_error(path))?),
+    };
+    Ok(Writer::new(inner, format))
+}
+
+/// Write all sequence pairs to `path`.
+pub fn write_pairs<'a>(
+    path: &Path,
+    pairs: impl IntoIterator<Item = (&'a Record, &'a Record)>,
+) -> Result<(), Error> {
+    let mut writer = create(path)?;
+    for (a, b) in pairs {
+        writer.write_pair(a, b).map_err(io_error(path))?;
+    }
+    writer.finish().map_err(io_error(path))
+}
+
+/// Reads records from a sequence file, one at a time.
+pub struct Reader<R> {
+    inner: R,
+    format: Format,
+    path: PathBuf,
+    /// The number of lines read so far.
+    line: usize,
+    /// A line that was read but not yet consumed, with its line number.
+    peeked: Option<(usize, Vec<u8>)>,
+    /// The number of records read so far, to check the `>`/`<` alternation in `.seq` files.
+    records: usize,
+    /// The ID of the current pair in a `.seq` file.
+    pair_id: Option<String>,
+    /// Set after a read error, after which no more lines are read.
+    broken: bool,
+}
+
+impl<R: BufRead> Reader<R> {
+    /// Read `format` from `inner`. `path` is only used in error messages.
+    pub fn new(inner: R, format: Format, path: impl Into<PathBuf>) -> Self {
+        Reader {
+            inner,
+            format,
+            path: path.into(),
+            line: 0,
+            peeked: None,
+            records: 0,
+            pair_id: None,
+            broken: false,
+        }
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Iterate over pairs of consecutive records.
+    pub fn pairs(self) -> Pairs<R> {
+        Pairs { reader: self }
+    }
+
+    fn error(&self, line: usize, message: impl Into<String>) -> Error {
+        Error {
+            path: self.path.clone(),
+            line: Some(line),
+            message: message.into(),
+        }
+    }
+
+    /// The next line and its line number, without the trailing newline.
+    fn next_line(&mut self) -> Result<Option<(usize, Vec<u8>)>, Error> {
+        if let Some(line) = self.peeked.take() {
+            return Ok(Some(line));
+        }
+        if self.broken {
+            return Ok(None);
+        }
+        let mut buf = vec![];
+        let n = self.inner.read_until(b'\n', &mut buf).map_err(|err| {
+            self.broken = true;
+            self.error(self.line + 1, err.to_string())
+        })?;
+        if n == 0 {
+            return Ok(None);
+        }
+        self.line += 1;
+        if buf.last() == Some(&b'\n') {
+            buf.pop();
+        }
+        if buf.last() == Some(&b'\r') {
+            buf.pop();
+        }
+        Ok(Some((self.line, buf)))
+    }
+
+    /// The next non-empty line and its line number.
+    fn next_non_empty_line(&mut self) -> Result<Option<(usize, Vec<u8>)>, Error> {
+        loop {
+            match self.next_line()? {
+                Some((_, line)) if line.is_empty() => continue,
+                line => return Ok(line),
+            }
+        }
+    }
+
+    /// The next record and the line it starts at.
+    pub fn next_record(&mut self) -> Result<Option<(usize, Record)>, Error> {
+        let record = match self.format {
+            Format::Seq => self.read_seq()?,
+            Format::Txt => self
+                .next_line()?
+                .map(|(line, seq)| (line, Record::new(seq))),
+            Format::Fasta => self.read_fasta()?,
+            Format::Fastq => self.read_fastq()?,
+        };
+        if record.is_some() {
+            self.records += 1;
+        }
+        Ok(record)
+    }
+
+    fn read_seq(&mut self) -> Result<Option<(usize, Record)>, Error> {
+        let Some((mut line, mut seq)) = self.next_non_empty_line()? else {
+            return Ok(None);
+        };
+        let first = self.records.is_multiple_of(2);
+        if first {
+            self.pair_id = None;
+            if let Some(id) = seq.strip_prefix(b"#") {
+                self.pair_id = Some(String::from_utf8_lossy(id).trim().to_string());
+                let Some(next) = self.next_non_empty_line()? else {
+                    return Err(self.error(line, "Pair ID header is not followed by a pair."));
+                };
+                (line, seq) = next;
+            }
+        }
+        let marker = if first { b'>' } else { b'<' };
+        match seq.split_first() {
+            Some((&c, seq)) if c == marker => Ok(Some((
+                line,
+                Record {
+                    id: self.pair_id.clone(),
+                    ..Record::new(seq.to_vec())
+                },
+            ))),
+            _ => Err(self.error(
+                line,
+                format!(
+                    "Expected a line starting with '{}'. Lines in a .seq file must alternately start with '>' and '<'.",
+                    marker as char
+                ),
+            )),
+        }
+    }
+
+    fn read_fasta(&mut self) -> Result<Option<(usize, Record)>, Error> {
+        let Some((line, header)) = self.next_non_empty_line()? else {
+            return Ok(None);
+        };
+        let Some(header) = header.strip_prefix(b">") else {
+            return Err(self.error(line, "Expected a FASTA header starting with '>'."));
+        };
+        let mut seq = vec![];
+        while let Some((l, s)) = self.next_line()? {
+            if s.starts_with(b">") {
+                self.peeked = Some((l, s));
+                break;
+            }
+            seq.extend_from_slice(&s);
+        }
+        Ok(Some((
+            line,
+            Record {
+                id: parse_id(header),
+                seq,
+                qual: None,
+            },
+        )))
+    }
+
+    fn read_fastq(&mut self) -> Result<Option<(usize, Record)>, Error> {
+        let Some((line, header)) = self.next_non_empty_line()? else {
+            return Ok(None);
+        };
+        let Some(header) = header.strip_prefix(b"@") else {
+            return Err(self.error(line, "Expected a FASTQ header starting with '@'."));
+        };
+        let truncated = |this: &Self| this.error(line, "Truncated FASTQ record.");
+        let Some((_, seq)) = self.next_line()? else {
+            return Err(truncated(self));
+        };
+        match self.next_line()? {
+            Some((_, sep)) if sep.starts_with(b"+") => {}
+            Some((l, _)) => {
+                return Err(self.error(l, "Expected a FASTQ separator line starting with '+'."))
+            }
+            None => return Err(truncated(self)),
+        }
+        let Some((l, qual)) = self.next_line()? else {
+            return Err(truncated(self));
+        };
+        if qual.len() != seq.len() {
+            return Err(self.error(
+                l,
+                format!(
+                    "Quality length {} differs from sequence length {}.",
+                    qual.len(),
+                    seq.len()
+                ),
+            ));
+        }
+        Ok(Some((
+            line,
+            Record {
+                id: parse_id(header),
+                seq,
+                qual: Some(qual),
+            },
+        )))
+    }
+}
+
+/// The id of a FASTA/FASTQ header: everything up to the first whitespace.
+fn parse_id(header: &[u8]) -> Option<String> {
+    let id = header
+        .split(|c| c.is_ascii_whitespace())
+        .next()
+        .unwrap_or_default();
+    (!id.is_empty()).then(|| String::from_utf8_lossy(id).into_owned())
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record()
+            .transpose()
+            .map(|r| r.map(|(_, record)| record))
+    }
+}
+
+/// Iterator over pairs of consecutive records. See [`Reader::pairs`].
+pub struct Pairs<R> {
+    reader: Reader<R>,
+}
+
+impl<R: BufRead> Iterator for Pairs<R> {
+    type Item = Result<(Record, Record), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (line, a) = match self.reader.next_record() {
+            Ok(Some(a)) => a,
+            Ok(None) => return None,
+            Err(err) => return Some(Err(err)),
+        };
+        Some(match self.reader.next_record() {
+            Ok(Some((_, b))) => Ok((a, b)),
+            Ok(None) => Err(self.reader.error(
+                line,
+                "The last sequence has no partner. Files must contain an even number of sequences.",
+            )),
+            Err(err) => {
+                // Drop `a`, so that iteration can continue with the next pair.
+                self.reader.records += 1;
+                Err(err)
+            }
+        })
+    }
+}
+
+/// Writes records to a sequence file.
+pub struct Writer<W> {
+    inner: W,
+    format: Format,
+    /// The number of records written so far, for the `>`/`<` alternation in `.seq` files.
+    records: usize,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(inner: W, format: Format) -> Self {
+        Writer {
+            inner,
+            format,
+            records: 0,
+        }
+    }
+
+    /// Write a single record.
+    ///
+    /// FASTA and FASTQ records without id are named by their 0-based index.
+    /// FASTQ records without qualities get quality `I` for all bases.
+    pub fn write_record(&mut self, record: &Record) -> io::Result<()> {
+        let id = match &record.id {
+            Some(id) => id.clone(),
+            None => self.records.to_string(),
+        };
+        let f = &mut self.inner;
+        match self.format {
+            Format::Seq => {
+                let first = self.records.is_multiple_of(2);
+                if let (true, Some(id)) = (first, &record.id) {
+                    writeln!(f, "#{id}")?;
+                }
+                f.write_all(if first { b">" } else { b"<" })?;
+                f.write_all(&record.seq)?;
+            }
+            Format::Txt => f.write_all(&record.seq)?,
+            Format::Fasta => {
+                writeln!(f, ">{id}")?;
+                f.write_all(&record.seq)?;
+            }
+            Format::Fastq => {
+                writeln!(f, "@{id}")?;
+                f.write_all(&record.seq)?;
+                f.write_all(b"\n+\n")?;
+                match &record.qual {
+                    Some(qual) => f.write_all(qual)?,
+                    None => f.write_all(&vec![b'I'; record.seq.len()])?,
+                }
+            }
+        }
+        f.write_all(b"\n")?;
+        self.records += 1;
+        Ok(())
+    }
+
+    pub fn write_pair(&mut self, a: &Record, b: &Record) -> io::Result<()> {
+        self.write_record(a)?;
+        self.write_record(b)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Writer<FileWriter> {
+    /// Complete the file, see [`FileWriter::finish`].
+    pub fn finish(self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The pairs in `contents`, read as `format`.
+    fn read(contents: &str, format: Format) -> Result<Vec<(Record, Record)>, Error> {
+        Reader::new(contents.as_bytes(), format, "test")
+            .pairs()
+            .collect()
+    }
+
+    fn record(id: Option<&str>, seq: &str, qual: Option<&str>) -> Record {
+        Record {
+            id: id.map(Into::into),
+            seq: seq.as_bytes().to_vec(),
+            qual: qual.map(|q| q.as_bytes().to_vec()),
+        }
+    }
+
+    #[test]
+    fn seq() {
+        let pairs = read("#p1\n>ACGT\n<AGT\n\n>AC\n<\n", Format::Seq).unwrap();
+        assert_eq!(
+            pairs,
+            [
+                (
+                    record(Some("p1"), "ACGT", None),
+                    record(Some("p1"), "AGT", None)
+                ),
+                (record(None, "AC", None), record(None, "", None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn txt() {
+        let pairs = read("ACGT\r\nAGT\n", Format::Txt).unwrap();
+        assert_eq!(
+            pairs,
+            [(record(None, "ACGT", None), record(None, "AGT", None))]
+        );
+    }
+
+    #[test]
+    fn fasta() {
+        let pairs = read(">a first read\nAC\nGT\n>b\nAGT\n", Format::Fasta).unwrap();
+        assert_eq!(
+            pairs,
+            [(
+                record(Some("a"), "ACGT", None),
+                record(Some("b"), "AGT", None)
+            )]
+        );
+    }
+
+    #[test]
+    fn fastq() {
+        let pairs = read("@a\nACGT\n+\nIIII\n@b x\nAGT\n+b\n#I#\n", Format::Fastq).unwrap();
+        assert_eq!(
+            pairs,
+            [(
+                record(Some("a"), "ACGT", Some("IIII")),
+                record(Some("b"), "AGT", Some("#I#"))
+            )]
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        let err = read(">ACGT\n<AGT\n>AC\n>AG\n", Format::Seq).unwrap_err();
+        assert_eq!(err.line, Some(4));
+        assert!(err
+            .to_string()
+            .starts_with("test:4: Expected a line starting with '<'."));
+
+        let err = read("@a\nACGT\n+\nIII\n", Format::Fastq).unwrap_err();
+        assert_eq!(err.line, Some(4));
+        assert_eq!(
+            err.message,
+            "Quality length 3 differs from sequence length 4."
+        );
+
+        let err = read(">a\nAC\n>b\nAG\n>c\nAT\n", Format::Fasta).unwrap_err();
+        assert_eq!(err.line, Some(5));
+
+        // Reading continues after a malformed pair.
+        let pairs: Vec<_> = Reader::new(">A\n>C\n>G\n<T\n".as_bytes(), Format::Seq, "test")
+            .pairs()
+            .collect();
+        assert_eq!(pairs.len(), 2);
+        assert!(pairs[0].is_err());
+        assert_eq!(
+            pairs[1].as_ref().unwrap(),
+            &(record(None, "G", None), record(None, "T", None))
+        );
+    }
+
+    #[test]
+    fn sniff() {
+        assert_eq!(Format::sniff(b"#p\n>A\n<C\n"), Some(Format::Seq));
+        assert_eq!(Format::sniff(b">A\n<C\n"), Some(Format::Seq));
+        assert_eq!(Format::sniff(b"\n>a\nACGT\n"), Some(Format::Fasta));
+        assert_eq!(Format::sniff(b"@a\nACGT\n"), Some(Format::Fastq));
+        assert_eq!(Format::sniff(b"ACGT\nAGT\n"), Some(Format::Txt));
+        assert_eq!(Format::sniff(b"1,2\n"), None);
+    }
+
+    #[test]
+    fn compressed_round_trip() {
+        let pairs = [
+            (
+                record(Some("a"), "ACGT", Some("IIII")),
+                record(Some("b"), "AGT", Some("III")),
+            ),
+            (
+                record(Some("c"), "", Some("")),
+                record(Some("d"), "T", Some("#")),
+            ),
+        ];
+        for (name, compression) in [("gz", Compression::Gzip), ("zst", Compression::Zstd)] {
+            let path = std::env::temp_dir().join(format!(
+                "pa-wrapper-{}-round-trip.fq.{name}",
+                std::process::id()
+            ));
+            write_pairs(&path, pairs.iter().map(|(a, b)| (a, b))).unwrap();
+            assert_eq!(detect_compression(&path).unwrap(), compression);
+            assert_eq!(read_pairs(&path).unwrap(), pairs);
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+}
@@ -3,6 +3,35 @@ use std::collections::HashMap;
 
 use pa_types::*;
 
+/// Construct an `AlignerInfo` for a backend crate.
+/// The version and git revision are resolved from `Cargo.lock` by `build.rs`.
+macro_rules! aligner_info {
+    (
+        name: $name:expr,
+        crate: $krate:literal,
+        env: $env:literal,
+        simd: $simd:expr,
+        citation: $citation:expr $(,)?
+    ) => {{
+        let simd: Option<&str> = $simd;
+        $crate::AlignerInfo {
+            name: $name.into(),
+            crate_name: $krate.into(),
+            version: option_env!(concat!("PA_DEP_VERSION_", $env)).map(Into::into),
+            git_revision: option_env!(concat!("PA_DEP_REV_", $env)).map(Into::into),
+            simd: simd.map(Into::into),
+            citation: $citation.into(),
+        }
+    }};
+}
+
+#[cfg(feature = "dp")]
+pub mod co_optimal;
+pub mod costs;
+pub use costs::{CostPreset, Costs, CostsOrPreset, SymmetricCosts};
+pub mod io;
+pub mod pretty;
+
 pub mod wrappers {
     #[cfg(feature = "astarpa")]
     pub mod astarpa;
@@ -10,6 +39,8 @@ pub mod wrappers {
     pub mod astarpa2;
     #[cfg(feature = "block_aligner")]
     pub mod block_aligner;
+    #[cfg(feature = "dp")]
+    pub mod dp;
     #[cfg(feature = "edlib")]
     pub mod edlib;
     #[cfg(feature = "ksw2")]
@@ -34,8 +65,85 @@ trait AlignerParamsTrait {
         max_len: usize,
     ) -> Result<Self::Aligner, &'static str>;
 
+    /// Instantiate the aligner with a cost model that may have different
+    /// costs for insertions and deletions, and a match bonus.
+    ///
+    /// By default, the match bonus is converted to an equivalent cost model,
+    /// and asymmetric costs are rejected.
+    /// Aligners that support asymmetric costs should override this.
+    fn build_with_costs(
+        &self,
+        costs: Costs,
+        trace: bool,
+        max_len: usize,
+    ) -> Result<Self::Aligner, &'static str> {
+        match costs.to_cost_model() {
+            Some(cm) => self.build(cm, trace, max_len),
+            None => Err("Aligner does not support different insertion and deletion costs"),
+        }
+    }
+
     /// Is the aligner exact?
     fn is_exact(&self) -> bool;
+
+    /// Instantiate the aligner for extension alignment.
+    ///
+    /// Only aligners whose parameters have `is_extension()` support this.
+    fn build_extension(
+        &self,
+        _costs: Costs,
+        _trace: bool,
+        _max_len: usize,
+    ) -> Result<Box<dyn ExtensionAlignerTrait>, &'static str> {
+        Err("Aligner does not support extension alignment")
+    }
+
+    /// Does the aligner do extension alignment, using `build_extension`?
+    fn is_extension(&self) -> bool {
+        false
+    }
+
+    /// Information about the backend of this aligner.
+    fn info(&self) -> AlignerInfo;
+}
+
+/// Information about the backend used by an aligner.
+///
+/// Stored with each benchmark result, so that results obtained with different
+/// versions of a backend can be told apart.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AlignerInfo {
+    /// Name of the aligner.
+    pub name: String,
+    /// The crate providing the aligner or the bindings to it.
+    pub crate_name: String,
+    /// Version of the crate, when known.
+    pub version: Option<String>,
+    /// Git revision of the crate, for git dependencies.
+    pub git_revision: Option<String>,
+    /// SIMD instruction set the aligner uses, if any.
+    pub simd: Option<String>,
+    /// How to cite the aligner.
+    pub citation: String,
+}
+
+/// The SIMD instruction set selected by backends that dispatch at runtime.
+#[allow(unused)]
+fn runtime_simd() -> Option<&'static str> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return Some("avx2");
+        }
+        if is_x86_feature_detected!("sse4.1") {
+            return Some("sse4.1");
+        }
+    }
+    if cfg!(target_arch = "aarch64") {
+        Some("neon")
+    } else {
+        None
+    }
 }
 
 /// Alignment statistics. Stats are summed over all sequence pairs in a dataset.
@@ -53,6 +161,16 @@ pub fn merge_stats(lhs: &mut AlignerStats, rhs: AlignerStats) {
     }
 }
 
+/// The result of an extension alignment.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extension {
+    /// The score of the extension, including the match bonus. Higher is better.
+    pub score: Cost,
+    /// The extension aligns `a[..a_end]` to `b[..b_end]`.
+    pub a_end: usize,
+    pub b_end: usize,
+}
+
 /// Generic pairwise global alignment interface.
 pub trait AlignerTrait {
     /// An alignment of sequences `a` and `b`.
@@ -61,6 +179,20 @@ pub trait AlignerTrait {
     fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>, AlignerStats);
 }
 
+/// Pairwise extension alignment interface, for aligners whose parameters have `is_extension()`.
+pub trait ExtensionAlignerTrait {
+    /// An extension alignment of `a` and `b`, starting at the start of both sequences,
+    /// as used for seed extension.
+    /// Returns where the best extension ends, and a trace of it when specified on construction of the aligner.
+    fn align_extension(&mut self, a: Seq, b: Seq) -> (Extension, Option<Cigar>, AlignerStats);
+}
+
+/// A global or an extension aligner, depending on `AlignerParams::is_extension`.
+pub enum AnyAligner {
+    Global(Box<dyn AlignerTrait>),
+    Extension(Box<dyn ExtensionAlignerTrait>),
+}
+
 /// Which algorithm to run and benchmark, along with algorithm-specific parameters.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, strum::EnumDiscriminants)]
 #[strum_discriminants(name(Aligner))]
@@ -72,19 +204,32 @@ pub enum AlignerParams {
     AstarPa2(wrappers::astarpa2::AstarPa2Params),
     #[cfg(feature = "block_aligner")]
     BlockAligner(wrappers::block_aligner::BlockAlignerParams),
+    #[cfg(feature = "dp")]
+    Dp(wrappers::dp::DpParams),
     #[cfg(feature = "edlib")]
+    #[serde(deserialize_with = "default_if_null")]
     Edlib(wrappers::edlib::EdlibParams),
     #[cfg(feature = "ksw2")]
     Ksw2(wrappers::ksw2::Ksw2Params),
     #[cfg(feature = "parasail")]
     ParasailStriped(wrappers::parasail::ParasailStripedParams),
     #[cfg(feature = "triple_accel")]
+    #[serde(deserialize_with = "default_if_null")]
     TripleAccel(wrappers::triple_accel::TripleAccelParams),
     #[cfg(feature = "wfa")]
     Wfa(wrappers::wfa::WfaParams),
     // Add more algorithms here!
 }
 
+/// Deserialize parameters that used to be a unit struct, which was serialized as `null`,
+/// so that older results and caches can still be read.
+#[allow(unused)]
+fn default_if_null<'de, D: serde::Deserializer<'de>, T: Deserialize<'de> + Default>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
 impl Aligner {
     pub fn default_params(&self) -> AlignerParams {
         use AlignerParams::*;
@@ -95,6 +240,8 @@ impl Aligner {
             Aligner::AstarPa2 => AstarPa2(Default::default()),
             #[cfg(feature = "block_aligner")]
             Aligner::BlockAligner => BlockAligner(Default::default()),
+            #[cfg(feature = "dp")]
+            Aligner::Dp => Dp(Default::default()),
             #[cfg(feature = "edlib")]
             Aligner::Edlib => Edlib(Default::default()),
             #[cfg(feature = "ksw2")]
@@ -110,23 +257,17 @@ impl Aligner {
 }
 
 impl AlignerParams {
-    /// Get an instance of the corresponding wrapper based on the algorithm.
-    ///
-    /// The bool indicates whether the aligner is exact.
-    pub fn build_aligner(
-        &self,
-        cm: CostModel,
-        trace: bool,
-        max_len: usize,
-    ) -> (Box<dyn AlignerTrait>, bool) {
+    fn as_type_erased(&self) -> &dyn TypeErasedAlignerParams {
         use AlignerParams::*;
-        let params: &dyn TypeErasedAlignerParams = match self {
+        match self {
             #[cfg(feature = "astarpa")]
             AstarPa(params) => params,
-            #[cfg(feature = "astarpa")]
+            #[cfg(feature = "astarpa2")]
             AstarPa2(params) => params,
             #[cfg(feature = "block_aligner")]
             BlockAligner(params) => params,
+            #[cfg(feature = "dp")]
+            Dp(params) => params,
             #[cfg(feature = "edlib")]
             Edlib(params) => params,
             #[cfg(feature = "ksw2")]
@@ -137,19 +278,99 @@ impl AlignerParams {
             TripleAccel(params) => params,
             #[cfg(feature = "wfa")]
             Wfa(params) => params,
-        };
-        let aligner = match params.build(cm, trace, max_len) {
+        }
+    }
+
+    /// Get an instance of the corresponding wrapper based on the algorithm.
+    ///
+    /// The bool indicates whether the aligner is exact.
+    pub fn build_aligner(
+        &self,
+        costs: impl Into<Costs>,
+        trace: bool,
+        max_len: usize,
+    ) -> (Box<dyn AlignerTrait>, bool) {
+        let costs = costs.into();
+        match self.try_build_aligner(costs, trace, max_len) {
             Ok(a) => a,
             Err(err) => {
                 eprintln!(
-                "\n\nBad aligner parameters:\n algo: {self:?}\n cm: sub={} open={} extend={}\n trace: {trace}\n error: {err}",
-                cm.sub, cm.open, cm.extend
+                "\n\nBad aligner parameters:\n algo: {self:?}\n costs: {costs:?}\n trace: {trace}\n error: {err}",
             );
                 std::process::exit(102);
             }
-        };
+        }
+    }
+
+    /// Like `build_aligner`, but returns an error for unsupported parameters instead of exiting.
+    pub fn try_build_aligner(
+        &self,
+        costs: impl Into<Costs>,
+        trace: bool,
+        max_len: usize,
+    ) -> Result<(Box<dyn AlignerTrait>, bool), &'static str> {
+        let params = self.as_type_erased();
+        let aligner = params.build(costs.into(), trace, max_len)?;
+        Ok((aligner, params.is_exact()))
+    }
+
+    /// Like `build_aligner`, but for extension alignment.
+    pub fn build_extension_aligner(
+        &self,
+        costs: impl Into<Costs>,
+        trace: bool,
+        max_len: usize,
+    ) -> Box<dyn ExtensionAlignerTrait> {
+        let costs = costs.into();
+        match self.try_build_extension_aligner(costs, trace, max_len) {
+            Ok(a) => a,
+            Err(err) => {
+                eprintln!(
+                "\n\nBad aligner parameters:\n algo: {self:?}\n costs: {costs:?}\n trace: {trace}\n error: {err}",
+            );
+                std::process::exit(102);
+            }
+        }
+    }
 
-        (aligner, params.is_exact())
+    /// Like `build_extension_aligner`, but returns an error for unsupported parameters instead of exiting.
+    pub fn try_build_extension_aligner(
+        &self,
+        costs: impl Into<Costs>,
+        trace: bool,
+        max_len: usize,
+    ) -> Result<Box<dyn ExtensionAlignerTrait>, &'static str> {
+        self.as_type_erased()
+            .build_extension(costs.into(), trace, max_len)
+    }
+
+    /// A global or extension aligner, as given by `is_extension`.
+    pub fn try_build_any_aligner(
+        &self,
+        costs: impl Into<Costs>,
+        trace: bool,
+        max_len: usize,
+    ) -> Result<AnyAligner, &'static str> {
+        Ok(if self.is_extension() {
+            AnyAligner::Extension(self.try_build_extension_aligner(costs, trace, max_len)?)
+        } else {
+            AnyAligner::Global(self.try_build_aligner(costs, trace, max_len)?.0)
+        })
+    }
+
+    /// Whether the aligner always returns the optimal cost.
+    pub fn is_exact(&self) -> bool {
+        self.as_type_erased().is_exact()
+    }
+
+    /// Whether the aligner does extension alignment instead of global alignment.
+    pub fn is_extension(&self) -> bool {
+        self.as_type_erased().is_extension()
+    }
+
+    /// Information about the backend of the aligner.
+    pub fn info(&self) -> AlignerInfo {
+        self.as_type_erased().info()
     }
 }
 
@@ -158,22 +379,44 @@ impl AlignerParams {
 trait TypeErasedAlignerParams {
     fn build(
         &self,
-        cm: CostModel,
+        costs: Costs,
         trace: bool,
         max_len: usize,
     ) -> Result<Box<dyn AlignerTrait>, &'static str>;
+    fn build_extension(
+        &self,
+        costs: Costs,
+        trace: bool,
+        max_len: usize,
+    ) -> Result<Box<dyn ExtensionAlignerTrait>, &'static str>;
     fn is_exact(&self) -> bool;
+    fn is_extension(&self) -> bool;
+    fn info(&self) -> AlignerInfo;
 }
 impl<A: AlignerTrait + 'static, T: AlignerParamsTrait<Aligner = A>> TypeErasedAlignerParams for T {
     fn build(
         &self,
-        cm: CostModel,
+        costs: Costs,
         trace: bool,
         max_len: usize,
     ) -> Result<Box<dyn AlignerTrait>, &'static str> {
-        Ok(Box::new(self.build(cm, trace, max_len)?))
+        Ok(Box::new(self.build_with_costs(costs, trace, max_len)?))
+    }
+    fn build_extension(
+        &self,
+        costs: Costs,
+        trace: bool,
+        max_len: usize,
+    ) -> Result<Box<dyn ExtensionAlignerTrait>, &'static str> {
+        AlignerParamsTrait::build_extension(self, costs, trace, max_len)
     }
     fn is_exact(&self) -> bool {
         self.is_exact()
     }
+    fn is_extension(&self) -> bool {
+        self.is_extension()
+    }
+    fn info(&self) -> AlignerInfo {
+        self.info()
+    }
 }
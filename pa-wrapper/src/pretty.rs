@@ -0,0 +1,131 @@
+//! Render alignments returned by any aligner as a path of positions, or as a
+//! human-readable three-line (query/match/target) view.
+
+use crate::*;
+
+/// The path of positions visited by `cigar`, starting at `(0, 0)` and ending at `(|a|, |b|)`.
+///
+/// `Pos(i, j)` means that `a[..i]` is aligned to `b[..j]`.
+pub fn cigar_to_path(cigar: &Cigar) -> Vec<Pos> {
+    let mut pos = Pos(0, 0);
+    let mut path = vec![pos];
+    for CigarElem { op, cnt } in &cigar.ops {
+        for _ in 0..*cnt {
+            match op {
+                CigarOp::Match | CigarOp::Sub => {
+                    pos.0 += 1;
+                    pos.1 += 1;
+                }
+                CigarOp::Del => pos.0 += 1,
+                CigarOp::Ins => pos.1 += 1,
+            }
+            path.push(pos);
+        }
+    }
+    path
+}
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// Render `cigar` as an alignment of `a` (top) and `b` (bottom), wrapped at `width` columns.
+///
+/// The middle line contains `|` for matches, `*` for substitutions, and a space for gaps.
+/// Each line is prefixed by the 1-based position of its first character.
+/// With `color`, matches are green, substitutions red, and gaps yellow, using ANSI escape codes.
+///
+/// Panics when `width` is 0.
+pub fn pretty_alignment(cigar: &Cigar, a: Seq, b: Seq, width: usize, color: bool) -> String {
+    assert!(width > 0, "Width must be positive");
+
+    // One column per cigar operation: (operation, char of a, char of b).
+    let mut columns = vec![];
+    let (mut i, mut j) = (0, 0);
+    for &CigarElem { op, cnt } in &cigar.ops {
+        for _ in 0..cnt {
+            columns.push(match op {
+                CigarOp::Match | CigarOp::Sub => {
+                    i += 1;
+                    j += 1;
+                    (op, a[i - 1], b[j - 1])
+                }
+                CigarOp::Del => {
+                    i += 1;
+                    (op, a[i - 1], b'-')
+                }
+                CigarOp::Ins => {
+                    j += 1;
+                    (op, b'-', b[j - 1])
+                }
+            });
+        }
+    }
+
+    let num_width = a.len().max(b.len()).to_string().len();
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    for block in columns.chunks(width) {
+        let mut top = String::new();
+        let mut mid = String::new();
+        let mut bot = String::new();
+        let mut last_color = None;
+        for &(op, ca, cb) in block {
+            let (marker, c) = match op {
+                CigarOp::Match => ('|', GREEN),
+                CigarOp::Sub => ('*', RED),
+                CigarOp::Del | CigarOp::Ins => (' ', YELLOW),
+            };
+            if color && last_color != Some(c) {
+                for line in [&mut top, &mut mid, &mut bot] {
+                    line.push_str(c);
+                }
+                last_color = Some(c);
+            }
+            top.push(ca as char);
+            mid.push(marker);
+            bot.push(cb as char);
+        }
+        if color {
+            for line in [&mut top, &mut mid, &mut bot] {
+                line.push_str(RESET);
+            }
+        }
+        // Count positions by operation, since the sequences may contain `-` themselves.
+        let di = block
+            .iter()
+            .filter(|(op, ..)| !matches!(op, CigarOp::Ins))
+            .count();
+        let dj = block
+            .iter()
+            .filter(|(op, ..)| !matches!(op, CigarOp::Del))
+            .count();
+        out += &format!("A {:>num_width$} {top} {}\n", i + 1, i + di);
+        out += &format!("  {:>num_width$} {mid}\n", "");
+        out += &format!("B {:>num_width$} {bot} {}\n\n", j + 1, j + dj);
+        i += di;
+        j += dj;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use CigarOp::*;
+
+    #[test]
+    fn mismatches_and_indels() {
+        let (a, b) = (b"ACGTA", b"AGGAC");
+        let cigar = Cigar::from_ops([Match, Sub, Match, Del, Match, Ins].into_iter());
+        assert_eq!(
+            cigar_to_path(&cigar),
+            [(0, 0), (1, 1), (2, 2), (3, 3), (4, 3), (5, 4), (5, 5)].map(|(i, j)| Pos(i, j))
+        );
+        assert_eq!(
+            pretty_alignment(&cigar, a, b, 4, false),
+            "A 1 ACGT 4\n    |*| \nB 1 AGG- 3\n\nA 5 A- 5\n    | \nB 4 AC 5\n\n"
+        );
+    }
+}
@@ -39,6 +39,16 @@ impl AlignerParamsTrait for AstarPaParams {
     fn is_exact(&self) -> bool {
         true
     }
+
+    fn info(&self) -> AlignerInfo {
+        aligner_info! {
+            name: "A*PA",
+            crate: "astarpa",
+            env: "ASTARPA",
+            simd: None,
+            citation: "Groot Koerkamp, R., & Ivanov, P. (2024). Exact global alignment using A* with chaining seed heuristic and match pruning. Bioinformatics, 40(3). https://doi.org/10.1093/bioinformatics/btae032",
+        }
+    }
 }
 
 impl AlignerTrait for Box<dyn AstarStatsAligner> {
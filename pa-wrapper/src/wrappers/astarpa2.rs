@@ -20,6 +20,16 @@ impl AlignerParamsTrait for AstarPa2Params {
     fn is_exact(&self) -> bool {
         true
     }
+
+    fn info(&self) -> AlignerInfo {
+        aligner_info! {
+            name: "A*PA2",
+            crate: "astarpa2",
+            env: "ASTARPA2",
+            simd: cfg!(target_feature = "avx2").then_some("avx2"),
+            citation: "Groot Koerkamp, R. (2024). A*PA2: up to 19x faster exact global alignment. WABI 2024. https://doi.org/10.4230/LIPIcs.WABI.2024.17",
+        }
+    }
 }
 
 impl AlignerTrait for Box<dyn astarpa2::AstarPa2StatsAligner> {
@@ -11,12 +11,17 @@ use ::block_aligner::percent_len;
 #[serde(deny_unknown_fields)]
 pub struct BlockAlignerParams {
     pub size: BlockAlignerSize,
+    /// X-drop threshold. When set, run extension alignment that stops once the
+    /// score drops this far below the best score. Requires a match bonus.
+    #[serde(default)]
+    pub x_drop: Option<i32>,
 }
 
 impl Default for BlockAlignerParams {
     fn default() -> Self {
         Self {
             size: BlockAlignerSize::Size(64, 4096),
+            x_drop: None,
         }
     }
 }
@@ -27,33 +32,36 @@ pub enum BlockAlignerSize {
     Percent(f32, f32),
 }
 
-enum BlockAlignerBlock {
-    Trace(Block<true, false>),
-    NoTrace(Block<false, false>),
+/// The block of a global (`X_DROP = false`) or X-drop extension (`X_DROP = true`) aligner.
+enum BlockAlignerBlock<const X_DROP: bool> {
+    Trace(Block<true, X_DROP>),
+    NoTrace(Block<false, X_DROP>),
 }
 
-pub struct BlockAligner {
+/// A global aligner, or an X-drop extension aligner when `X_DROP` is set.
+pub struct BlockAligner<const X_DROP: bool = false> {
     params: BlockAlignerParams,
     matrix: NucMatrix,
     gaps: Gaps,
-    block: BlockAlignerBlock,
+    block: BlockAlignerBlock<X_DROP>,
     a: PaddedBytes,
     b: PaddedBytes,
+    /// Cigar buffer for the traceback, reused between calls.
+    cigar: ::block_aligner::cigar::Cigar,
+    /// The sequence length `cigar` was allocated for.
+    cigar_len: usize,
     s: ScoreModel,
 }
 
-impl AlignerParamsTrait for BlockAlignerParams {
-    type Aligner = BlockAligner;
-
-    fn build(
+impl BlockAlignerParams {
+    fn make_aligner<const X_DROP: bool>(
         &self,
-        cm: CostModel,
+        matrix: NucMatrix,
+        gaps: Gaps,
+        s: ScoreModel,
         trace: bool,
         max_len: usize,
-    ) -> Result<Self::Aligner, &'static str> {
-        if !cm.is_affine() {
-            return Err("BlockAligner only works for affine cost models");
-        }
+    ) -> BlockAligner<X_DROP> {
         let max_size = match self.size {
             BlockAlignerSize::Size(_, max) => max,
             BlockAlignerSize::Percent(_, max) => percent_len(max_len, max),
@@ -63,40 +71,166 @@ impl AlignerParamsTrait for BlockAlignerParams {
         } else {
             BlockAlignerBlock::NoTrace(Block::new(max_len, max_len, max_size))
         };
-        let s = ScoreModel::from_costs(cm);
-        let matrix = NucMatrix::new_simple(s.r#match as i8, s.sub as i8);
-        let gaps = Gaps {
-            open: (s.open + s.extend) as i8,
-            extend: s.extend as i8,
-        };
         let a = PaddedBytes::new::<NucMatrix>(max_len, max_size);
         let b = PaddedBytes::new::<NucMatrix>(max_len, max_size);
+        // The cigar buffer is allocated for the longest input, and grown by
+        // `reserve_cigar` when longer sequences are aligned.
+        let cigar_len = if trace { max_len } else { 0 };
+        let cigar = ::block_aligner::cigar::Cigar::new(cigar_len, cigar_len);
 
-        Ok(Self::Aligner {
-            params: self.clone(),
+        BlockAligner {
+            params: *self,
             matrix,
             gaps,
             block,
             a,
             b,
+            cigar,
+            cigar_len,
             s,
-        })
+        }
+    }
+}
+
+impl AlignerParamsTrait for BlockAlignerParams {
+    type Aligner = BlockAligner;
+
+    fn build(
+        &self,
+        cm: CostModel,
+        trace: bool,
+        max_len: usize,
+    ) -> Result<Self::Aligner, &'static str> {
+        if self.x_drop.is_some() {
+            return Err("BlockAligner with x_drop only does extension alignment");
+        }
+        if !cm.is_affine() {
+            return Err("BlockAligner only works for affine cost models");
+        }
+        let s = ScoreModel::from_costs(cm);
+        check_i8(s.r#match, -s.sub, -(s.open + s.extend))?;
+        let matrix = NucMatrix::new_simple(s.r#match as i8, s.sub as i8);
+        let gaps = Gaps {
+            open: (s.open + s.extend) as i8,
+            extend: s.extend as i8,
+        };
+        Ok(self.make_aligner(matrix, gaps, s, trace, max_len))
+    }
+
+    fn build_extension(
+        &self,
+        costs: Costs,
+        trace: bool,
+        max_len: usize,
+    ) -> Result<Box<dyn ExtensionAlignerTrait>, &'static str> {
+        if self.x_drop.is_none() {
+            return Err("BlockAligner extension alignment requires x_drop");
+        }
+        let cm = costs
+            .to_cost_model()
+            .ok_or("BlockAligner does not support different insertion and deletion costs")?;
+        if !cm.is_affine() {
+            return Err("BlockAligner only works for affine cost models");
+        }
+        // Extension alignment is not global, so the match bonus must be used as-is.
+        let Some(r#match) = costs.match_bonus else {
+            return Err("BlockAligner X-drop extension alignment requires a match bonus");
+        };
+        let (open, extend) = costs.ins();
+        check_i8(r#match, costs.sub, open + extend)?;
+        let matrix = NucMatrix::new_simple(r#match as i8, -costs.sub as i8);
+        let gaps = Gaps {
+            open: -(open + extend) as i8,
+            extend: -extend as i8,
+        };
+        let aligner: BlockAligner<true> =
+            self.make_aligner(matrix, gaps, ScoreModel::from_costs(cm), trace, max_len);
+        Ok(Box::new(aligner))
     }
 
     fn is_exact(&self) -> bool {
         false
     }
+
+    fn is_extension(&self) -> bool {
+        self.x_drop.is_some()
+    }
+
+    fn info(&self) -> AlignerInfo {
+        aligner_info! {
+            name: "Block aligner",
+            crate: "block-aligner",
+            env: "BLOCK_ALIGNER",
+            // Must match the features enabled in Cargo.toml.
+            simd: if cfg!(target_arch = "x86_64") {
+                Some("simd_avx2")
+            } else if cfg!(target_arch = "aarch64") {
+                Some("simd_neon")
+            } else {
+                None
+            },
+            citation: "Liu, D., & Steinegger, M. (2023). Block aligner: an adaptive SIMD-accelerated aligner for sequences and position-specific scoring matrices. Bioinformatics, 39(8). https://doi.org/10.1093/bioinformatics/btad487",
+        }
+    }
 }
 
-impl AlignerTrait for BlockAligner {
-    fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>, AlignerStats) {
+impl<const X_DROP: bool> BlockAligner<X_DROP> {
+    /// The range of block sizes to use for the given sequences.
+    fn size(&self, a: Seq, b: Seq) -> std::ops::RangeInclusive<usize> {
         let max_len = a.len().max(b.len());
-        let size = match self.params.size {
+        match self.params.size {
             BlockAlignerSize::Size(min, max) => min..=max,
             BlockAlignerSize::Percent(min, max) => {
                 percent_len(max_len, min)..=percent_len(max_len, max)
             }
-        };
+        }
+    }
+
+    /// Grow the cigar buffer when tracing sequences longer than it was allocated for,
+    /// since block aligner writes to it without bounds checks.
+    fn reserve_cigar(&mut self, a: Seq, b: Seq) {
+        let len = a.len().max(b.len());
+        if matches!(self.block, BlockAlignerBlock::Trace(_)) && len > self.cigar_len {
+            self.cigar = ::block_aligner::cigar::Cigar::new(len, len);
+            self.cigar_len = len;
+        }
+    }
+}
+
+/// Block aligner takes its scores as `i8`, and computes with `i16` scores relative to
+/// a running offset, so that scores do not overflow as long as the parameters fit.
+fn check_i8(r#match: Cost, sub: Cost, open: Cost) -> Result<(), &'static str> {
+    if [r#match, sub, open]
+        .into_iter()
+        .any(|c| c > i8::MAX as Cost)
+    {
+        return Err("BlockAligner requires scores that fit in 8 bits");
+    }
+    Ok(())
+}
+
+fn convert_cigar(ba_cigar: &::block_aligner::cigar::Cigar) -> Cigar {
+    let ops = (0..ba_cigar.len())
+        .map(|i| {
+            let ::block_aligner::cigar::OpLen { op, len } = ba_cigar.get(i);
+            let op = match op {
+                ::block_aligner::cigar::Operation::Eq => CigarOp::Match,
+                ::block_aligner::cigar::Operation::X => CigarOp::Sub,
+                // I and D are opposite of Ins and Del
+                ::block_aligner::cigar::Operation::D => CigarOp::Ins,
+                ::block_aligner::cigar::Operation::I => CigarOp::Del,
+                _ => unreachable!(),
+            };
+            CigarElem { op, cnt: len as _ }
+        })
+        .collect();
+    Cigar { ops }
+}
+
+impl AlignerTrait for BlockAligner {
+    fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>, AlignerStats) {
+        let size = self.size(a, b);
+        self.reserve_cigar(a, b);
         self.a.set_bytes::<NucMatrix>(a, *size.end());
         self.b.set_bytes::<NucMatrix>(b, *size.end());
         match &mut self.block {
@@ -111,28 +245,64 @@ impl AlignerTrait for BlockAligner {
             BlockAlignerBlock::Trace(block) => {
                 block.align(&self.a, &self.b, &self.matrix, self.gaps, size, 0);
 
-                let mut ba_cigar = ::block_aligner::cigar::Cigar::new(self.a.len(), self.b.len());
-                block
-                    .trace()
-                    .cigar_eq(&self.a, &self.b, self.a.len(), self.b.len(), &mut ba_cigar);
-                let ops = (0..ba_cigar.len())
-                    .map(|i| {
-                        let ::block_aligner::cigar::OpLen { op, len } = ba_cigar.get(i);
-                        let op = match op {
-                            ::block_aligner::cigar::Operation::Eq => CigarOp::Match,
-                            ::block_aligner::cigar::Operation::X => CigarOp::Sub,
-                            // I and D are opposite of Ins and Del
-                            ::block_aligner::cigar::Operation::D => CigarOp::Ins,
-                            ::block_aligner::cigar::Operation::I => CigarOp::Del,
-                            _ => unreachable!(),
-                        };
-                        CigarElem { op, cnt: len as _ }
-                    })
-                    .collect();
+                block.trace().cigar_eq(
+                    &self.a,
+                    &self.b,
+                    self.a.len(),
+                    self.b.len(),
+                    &mut self.cigar,
+                );
 
                 (
                     self.s.global_cost(block.res().score, a.len(), b.len()),
-                    Some(Cigar { ops }),
+                    Some(convert_cigar(&self.cigar)),
+                    AlignerStats::default(),
+                )
+            }
+        }
+    }
+}
+
+impl ExtensionAlignerTrait for BlockAligner<true> {
+    fn align_extension(&mut self, a: Seq, b: Seq) -> (Extension, Option<Cigar>, AlignerStats) {
+        let size = self.size(a, b);
+        self.reserve_cigar(a, b);
+        self.a.set_bytes::<NucMatrix>(a, *size.end());
+        self.b.set_bytes::<NucMatrix>(b, *size.end());
+        let x_drop = self.params.x_drop.unwrap();
+        match &mut self.block {
+            BlockAlignerBlock::NoTrace(block) => {
+                block.align(&self.a, &self.b, &self.matrix, self.gaps, size, x_drop);
+                let res = block.res();
+                (
+                    Extension {
+                        score: res.score,
+                        a_end: res.query_idx,
+                        b_end: res.reference_idx,
+                    },
+                    None,
+                    AlignerStats::default(),
+                )
+            }
+            BlockAlignerBlock::Trace(block) => {
+                block.align(&self.a, &self.b, &self.matrix, self.gaps, size, x_drop);
+                let res = block.res();
+
+                block.trace().cigar_eq(
+                    &self.a,
+                    &self.b,
+                    res.query_idx,
+                    res.reference_idx,
+                    &mut self.cigar,
+                );
+
+                (
+                    Extension {
+                        score: res.score,
+                        a_end: res.query_idx,
+                        b_end: res.reference_idx,
+                    },
+                    Some(convert_cigar(&self.cigar)),
                     AlignerStats::default(),
                 )
             }
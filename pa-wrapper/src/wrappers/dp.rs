@@ -0,0 +1,356 @@
+//! A simple reference implementation of the Gotoh DP for affine costs.
+//!
+//! This is slow (quadratic time, and quadratic memory when a trace is needed),
+//! but supports all cost models, including asymmetric insertion and deletion costs.
+//! It is mostly useful as ground truth on small inputs.
+use crate::*;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DpParams;
+
+pub struct Dp {
+    costs: Costs,
+    trace: bool,
+    /// The two rows used for cost-only alignment, reused between calls.
+    rows: (Vec<Cell>, Vec<Cell>),
+}
+
+impl AlignerParamsTrait for DpParams {
+    type Aligner = Dp;
+
+    fn build(
+        &self,
+        cm: CostModel,
+        trace: bool,
+        max_len: usize,
+    ) -> Result<Self::Aligner, &'static str> {
+        self.build_with_costs(cm.into(), trace, max_len)
+    }
+
+    fn build_with_costs(
+        &self,
+        costs: Costs,
+        trace: bool,
+        _max_len: usize,
+    ) -> Result<Self::Aligner, &'static str> {
+        Ok(Dp::new(costs, trace))
+    }
+
+    fn is_exact(&self) -> bool {
+        true
+    }
+
+    fn info(&self) -> AlignerInfo {
+        AlignerInfo {
+            name: "DP".into(),
+            crate_name: "pa-wrapper".into(),
+            version: Some(env!("CARGO_PKG_VERSION").into()),
+            git_revision: None,
+            simd: None,
+            citation: "Gotoh, O. (1982). An improved algorithm for matching biological sequences. Journal of Molecular Biology, 162(3), 705–708. https://doi.org/10.1016/0022-2836(82)90398-9".into(),
+        }
+    }
+}
+
+const INF: Cost = Cost::MAX / 2;
+
+/// The DP state of a cell: the costs of the best alignments ending in a
+/// match/substitution, an insertion, and a deletion.
+#[derive(Clone, Copy)]
+pub(crate) struct Cell {
+    m: Cost,
+    ins: Cost,
+    del: Cost,
+}
+
+/// The layers of the DP: the last operation of an alignment.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum State {
+    M,
+    Ins,
+    Del,
+}
+
+impl Cell {
+    const INF: Cell = Cell {
+        m: INF,
+        ins: INF,
+        del: INF,
+    };
+
+    pub(crate) fn min(&self) -> Cost {
+        self.m.min(self.ins).min(self.del)
+    }
+
+    pub(crate) fn get(&self, state: State) -> Cost {
+        match state {
+            State::M => self.m,
+            State::Ins => self.ins,
+            State::Del => self.del,
+        }
+    }
+
+    /// The states that have the minimal cost.
+    pub(crate) fn optimal_states(&self) -> impl Iterator<Item = State> + '_ {
+        let min = self.min();
+        [State::M, State::Ins, State::Del]
+            .into_iter()
+            .filter(move |&s| self.get(s) == min)
+    }
+}
+
+impl Dp {
+    pub(crate) fn new(costs: Costs, trace: bool) -> Self {
+        Dp {
+            costs: costs.without_match_bonus(),
+            trace,
+            rows: (vec![], vec![]),
+        }
+    }
+
+    /// Compute a cell from its left, top, and diagonal neighbours.
+    /// Gaps can only be opened after a match/substitution or a gap of the other type.
+    #[inline]
+    fn cell(&self, is_match: bool, left: &Cell, top: &Cell, diag: &Cell) -> Cell {
+        let (ins_open, ins_extend) = self.costs.ins();
+        let (del_open, del_extend) = self.costs.del();
+        let sub = if is_match { 0 } else { self.costs.sub };
+        Cell {
+            m: diag.min() + sub,
+            ins: (left.m.min(left.del) + ins_open).min(left.ins) + ins_extend,
+            del: (top.m.min(top.ins) + del_open).min(top.del) + del_extend,
+        }
+    }
+
+    /// Boundary cell in the first row or column.
+    fn boundary(&self, i: usize, j: usize) -> Cell {
+        let (ins_open, ins_extend) = self.costs.ins();
+        let (del_open, del_extend) = self.costs.del();
+        match (i, j) {
+            (0, 0) => Cell {
+                m: 0,
+                ins: INF,
+                del: INF,
+            },
+            (0, j) => Cell {
+                ins: ins_open + j as Cost * ins_extend,
+                ..Cell::INF
+            },
+            (i, _) => Cell {
+                del: del_open + i as Cost * del_extend,
+                ..Cell::INF
+            },
+        }
+    }
+
+    /// Compute only the cost, using two rows of memory.
+    fn cost(&mut self, a: Seq, b: Seq) -> Cost {
+        let (mut prev, mut next) = std::mem::take(&mut self.rows);
+        prev.clear();
+        prev.extend((0..=b.len()).map(|j| self.boundary(0, j)));
+        next.clear();
+        next.resize(b.len() + 1, Cell::INF);
+        for i in 1..=a.len() {
+            next[0] = self.boundary(i, 0);
+            for j in 1..=b.len() {
+                next[j] = self.cell(a[i - 1] == b[j - 1], &next[j - 1], &prev[j], &prev[j - 1]);
+            }
+            std::mem::swap(&mut prev, &mut next);
+        }
+        let cost = prev[b.len()].min();
+        self.rows = (prev, next);
+        cost
+    }
+
+    /// Compute the full matrix, with cell `(i, j)` at index `i * (|b| + 1) + j`.
+    pub(crate) fn matrix(&self, a: Seq, b: Seq) -> Vec<Cell> {
+        let w = b.len() + 1;
+        let mut dp = vec![Cell::INF; (a.len() + 1) * w];
+        for i in 0..=a.len() {
+            for j in 0..=b.len() {
+                dp[i * w + j] = if i == 0 || j == 0 {
+                    self.boundary(i, j)
+                } else {
+                    self.cell(
+                        a[i - 1] == b[j - 1],
+                        &dp[i * w + j - 1],
+                        &dp[(i - 1) * w + j],
+                        &dp[(i - 1) * w + j - 1],
+                    )
+                };
+            }
+        }
+        dp
+    }
+
+    /// The predecessors of `state` at `(i, j)` on optimal paths to it, as `(i, j, state)`.
+    /// Extending a gap is preferred over opening one.
+    pub(crate) fn predecessors(
+        &self,
+        dp: &[Cell],
+        a: Seq,
+        b: Seq,
+        (i, j, state): (usize, usize, State),
+    ) -> Vec<(usize, usize, State)> {
+        let w = b.len() + 1;
+        let cost = dp[i * w + j].get(state);
+        if cost >= INF {
+            return vec![];
+        }
+        let (pi, pj, step, open) = match state {
+            State::M => {
+                if i == 0 || j == 0 {
+                    return vec![];
+                }
+                let sub = if a[i - 1] == b[j - 1] {
+                    0
+                } else {
+                    self.costs.sub
+                };
+                (i - 1, j - 1, sub, 0)
+            }
+            State::Ins => {
+                let (open, extend) = self.costs.ins();
+                (i, j - 1, extend, open)
+            }
+            State::Del => {
+                let (open, extend) = self.costs.del();
+                (i - 1, j, extend, open)
+            }
+        };
+        let prev = &dp[pi * w + pj];
+        let states = match state {
+            State::M => [State::M, State::Ins, State::Del],
+            State::Ins => [State::Ins, State::M, State::Del],
+            State::Del => [State::Del, State::M, State::Ins],
+        };
+        states
+            .into_iter()
+            .filter(|&s| {
+                // Opening a gap is needed when coming from a different state.
+                let open = if s == state || state == State::M {
+                    0
+                } else {
+                    open
+                };
+                prev.get(s) < INF && prev.get(s) + open + step == cost
+            })
+            .map(|s| (pi, pj, s))
+            .collect()
+    }
+
+    /// Compute the full matrix and trace back an optimal alignment.
+    fn cost_and_cigar(&self, a: Seq, b: Seq) -> (Cost, Cigar) {
+        let w = b.len() + 1;
+        let dp = self.matrix(a, b);
+        let end = &dp[a.len() * w + b.len()];
+        let cost = end.min();
+        let mut ops = vec![];
+        let mut cur = (a.len(), b.len(), end.optimal_states().next().unwrap());
+        while (cur.0, cur.1) != (0, 0) {
+            ops.push(op(a, b, cur));
+            cur = self.predecessors(&dp, a, b, cur)[0];
+        }
+        ops.reverse();
+        (cost, Cigar::from_ops(ops.into_iter()))
+    }
+}
+
+/// The cigar operation of the last character of `state` at `(i, j)`.
+pub(crate) fn op(a: Seq, b: Seq, (i, j, state): (usize, usize, State)) -> CigarOp {
+    match state {
+        State::M if a[i - 1] == b[j - 1] => CigarOp::Match,
+        State::M => CigarOp::Sub,
+        State::Ins => CigarOp::Ins,
+        State::Del => CigarOp::Del,
+    }
+}
+
+impl AlignerTrait for Dp {
+    fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>, AlignerStats) {
+        if self.trace {
+            let (cost, cigar) = self.cost_and_cigar(a, b);
+            (cost, Some(cigar), AlignerStats::default())
+        } else {
+            (self.cost(a, b), None, AlignerStats::default())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The cost of aligning `a` and `b`, checking that the traced alignment has the same cost.
+    fn cost(costs: Costs, a: Seq, b: Seq) -> Cost {
+        let cost = Dp::new(costs, false).align(a, b).0;
+        let (trace_cost, cigar, _) = Dp::new(costs, true).align(a, b);
+        assert_eq!(trace_cost, cost);
+        assert_eq!(costs.verify(&cigar.unwrap(), a, b), Ok(cost));
+        cost
+    }
+
+    #[test]
+    fn unit() {
+        let unit = CostModel::unit().into();
+        assert_eq!(cost(unit, b"", b""), 0);
+        assert_eq!(cost(unit, b"ACGT", b""), 4);
+        assert_eq!(cost(unit, b"", b"ACG"), 3);
+        assert_eq!(cost(unit, b"ACGT", b"ACGT"), 0);
+        assert_eq!(cost(unit, b"ACGT", b"AGGT"), 1);
+        assert_eq!(cost(unit, b"ACGT", b"AGT"), 1);
+        assert_eq!(cost(unit, b"AAAA", b"CCCCC"), 5);
+    }
+
+    #[test]
+    fn affine() {
+        let costs = CostModel {
+            sub: 1,
+            open: 2,
+            extend: 1,
+        }
+        .into();
+        assert_eq!(cost(costs, b"ACGT", b"AGGT"), 1);
+        // One gap of length 3.
+        assert_eq!(cost(costs, b"AAAAGGGCCCC", b"AAAACCCC"), 2 + 3);
+        assert_eq!(cost(costs, b"AAAACCCC", b"AAAAGGGCCCC"), 2 + 3);
+        // Two substitutions are cheaper than a deletion and an insertion.
+        assert_eq!(cost(costs, b"ACGT", b"AGCT"), 2);
+    }
+
+    #[test]
+    fn asymmetric() {
+        // Insertions cost `1 + l`, deletions cost `3 + 2 * l`.
+        let costs = Costs {
+            ins_open: Some(1),
+            ins_extend: Some(1),
+            del_open: Some(3),
+            del_extend: Some(2),
+            ..CostModel {
+                sub: 10,
+                open: 0,
+                extend: 1,
+            }
+            .into()
+        };
+        assert_eq!(cost(costs, b"ACCC", b"A"), 3 + 3 * 2);
+        assert_eq!(cost(costs, b"A", b"ACCC"), 1 + 3);
+        // Deleting C and inserting G is cheaper than substituting C by G.
+        assert_eq!(cost(costs, b"AC", b"AG"), (3 + 2) + (1 + 1));
+    }
+
+    #[test]
+    fn match_bonus() {
+        // sub: 4, open: 6, extend: 1, match_bonus: 1.
+        let costs = CostPreset::BwaMem.costs();
+        // 3 matches and a substitution.
+        assert_eq!(costs.score(cost(costs, b"ACGT", b"AGGT"), 4, 4), 3 - 4);
+        // 3 matches and a deletion.
+        assert_eq!(costs.score(cost(costs, b"ACGT", b"AGT"), 4, 3), 3 - (6 + 1));
+        // 8 matches outweigh opening a gap of length 2.
+        assert_eq!(
+            costs.score(cost(costs, b"AAAACCCCGG", b"GGAAAACCCC"), 10, 10),
+            8 - 2 * (6 + 2)
+        );
+    }
+}
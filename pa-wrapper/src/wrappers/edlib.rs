@@ -3,7 +3,13 @@ use edlib_rs::*;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[serde(deny_unknown_fields)]
-pub struct EdlibParams;
+pub struct EdlibParams {
+    /// Only find alignments with cost at most `k`, using a band.
+    /// By default, Edlib doubles `k` until an alignment is found.
+    /// Alignments with larger cost are not found, so banded runs are not exact.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub k: Option<usize>,
+}
 
 pub struct Edlib {
     config: EdlibAlignConfigRs<'static>,
@@ -26,11 +32,24 @@ impl AlignerParamsTrait for EdlibParams {
         if trace {
             config.task = EdlibAlignTaskRs::EDLIB_TASK_PATH;
         }
+        if let Some(k) = self.k {
+            config.k = k as i32;
+        }
         Ok(Self::Aligner { config })
     }
 
     fn is_exact(&self) -> bool {
-        true
+        self.k.is_none()
+    }
+
+    fn info(&self) -> AlignerInfo {
+        aligner_info! {
+            name: "Edlib",
+            crate: "edlib_rs",
+            env: "EDLIB_RS",
+            simd: None,
+            citation: "Šošić, M., & Šikić, M. (2017). Edlib: a C/C++ library for fast, exact sequence alignment using edit distance. Bioinformatics, 33(9), 1394–1395. https://doi.org/10.1093/bioinformatics/btw753",
+        }
     }
 }
 
@@ -39,6 +58,12 @@ impl AlignerTrait for Edlib {
         let result = edlibAlignRs(a, b, &self.config);
         assert!(result.status == EDLIB_RS_STATUS_OK);
         let cost = result.getDistance();
+        // Edlib returns -1 when the distance is larger than `k`.
+        if cost == -1 {
+            let mut stats = AlignerStats::default();
+            stats.insert("out_of_band".into(), 1.);
+            return (Cost::MAX, None, stats);
+        }
         let cigar = result.getAlignment().map(|alignment| {
             Cigar::from_ops(alignment.into_iter().map(|op| match op {
                 0 => CigarOp::Match,
@@ -52,3 +77,17 @@ impl AlignerTrait for Edlib {
         (cost, cigar, AlignerStats::default())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Older results store the parameters of this former unit struct as `null`.
+    #[test]
+    fn deserialize_null_params() {
+        let params: AlignerParams = serde_json::from_str(r#"{"Edlib":null}"#).unwrap();
+        assert_eq!(params, AlignerParams::Edlib(Default::default()));
+        let params: AlignerParams = serde_json::from_str(r#"{"Edlib":{"k":10}}"#).unwrap();
+        assert_eq!(params, AlignerParams::Edlib(EdlibParams { k: Some(10) }));
+    }
+}
@@ -21,6 +21,29 @@ pub struct Ksw2Params {
     pub method: Ksw2Method,
     #[serde(default = "band_doubling_enabled")]
     pub band_doubling: bool,
+    /// Fixed band width, when `band_doubling` is disabled. Disabled by default.
+    /// The optimal alignment may fall outside the band, so banded runs are not exact.
+    #[serde(default)]
+    pub band: Option<usize>,
+    /// Initial band width for band doubling. Defaults to ksw2's choice.
+    #[serde(default)]
+    pub band_doubling_start: Option<usize>,
+    /// Run extension alignment instead of global alignment.
+    /// Requires an `Extension*` method and a match bonus.
+    #[serde(default)]
+    pub extension: Option<Ksw2Extension>,
+}
+
+/// Parameters for extension alignment with ksw2.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Ksw2Extension {
+    /// Stop extending once the score drops this far below the best score,
+    /// (ignoring the cost of the gap between them). Negative to disable.
+    pub zdrop: i32,
+    /// Bonus for reaching the end of `a`. Only used by `ExtensionSuzukiSse`.
+    #[serde(default)]
+    pub end_bonus: i32,
 }
 fn band_doubling_enabled() -> bool {
     true
@@ -31,6 +54,9 @@ impl Default for Ksw2Params {
         Ksw2Params {
             method: Ksw2Method::GlobalSuzukiSse,
             band_doubling: true,
+            band: None,
+            band_doubling_start: None,
+            extension: None,
         }
     }
 }
@@ -44,22 +70,46 @@ pub struct Ksw2 {
     score_matrix: [i8; M * M],
     open: i8,
     extend: i8,
+    /// The costs of global alignments, which may be equivalent to asymmetric costs.
+    costs: SymmetricCosts,
+    /// Encoded input sequences, reused between calls.
+    a_mapped: Vec<u8>,
+    b_mapped: Vec<u8>,
+    /// Cigar buffer owned by ksw2, which grows it with `realloc` as needed.
+    /// Reused between calls and freed on drop.
+    cigar: *mut u32,
+    /// Allocated length of `cigar`.
+    m_cigar: i32,
 }
 
-impl AlignerParamsTrait for Ksw2Params {
-    type Aligner = Ksw2;
-    fn build(
+impl Drop for Ksw2 {
+    fn drop(&mut self) {
+        unsafe { libc::free(self.cigar as *mut c_void) };
+    }
+}
+
+impl Ksw2Params {
+    fn make_aligner(
         &self,
-        cm: CostModel,
+        r#match: Cost,
+        sub: Cost,
+        open: Cost,
+        extend: Cost,
         trace: bool,
-        _max_len: usize,
-    ) -> Result<Self::Aligner, &'static str> {
+    ) -> Result<Ksw2, &'static str> {
+        // ksw2 takes its scores as `i8`, and computes with them exactly when they fit.
+        if [r#match, sub, open + extend]
+            .into_iter()
+            .any(|c| c > i8::MAX as Cost)
+        {
+            return Err("Ksw2 requires scores that fit in 8 bits");
+        }
         // NOTE: The score matrix isn't actually used: only m[0]=match and
         // m[1]=mismatch are used, unless the flag KSW_EZ_GENERIC_SC=0x04 is set which says the matrix is arbitrary.
         let mut score_matrix = [0; M * M];
         for i in 0..M - 1 {
             for j in 0..M - 1 {
-                score_matrix[(M * i + j) as usize] = if i == j { 0 } else { -cm.sub as i8 };
+                score_matrix[M * i + j] = if i == j { r#match as i8 } else { -sub as i8 };
             }
         }
 
@@ -68,31 +118,123 @@ impl AlignerParamsTrait for Ksw2Params {
         encoding[b'C' as usize] = 1;
         encoding[b'G' as usize] = 2;
         encoding[b'T' as usize] = 3;
-        Ok(Self::Aligner {
-            params: self.clone(),
+        Ok(Ksw2 {
+            params: *self,
             trace,
             encoding,
             score_matrix,
-            open: cm.open as _,
-            extend: cm.extend as _,
+            open: open as _,
+            extend: extend as _,
+            costs: CostModel { sub, open, extend }.into(),
+            a_mapped: vec![],
+            b_mapped: vec![],
+            cigar: std::ptr::null_mut(),
+            m_cigar: 0,
         })
     }
+}
+
+impl AlignerParamsTrait for Ksw2Params {
+    type Aligner = Ksw2;
+    fn build(
+        &self,
+        cm: CostModel,
+        trace: bool,
+        _max_len: usize,
+    ) -> Result<Self::Aligner, &'static str> {
+        if self.extension.is_some() {
+            return Err("Ksw2 with extension parameters only does extension alignment");
+        }
+        if self.band_doubling && self.band.is_some() {
+            return Err("Ksw2 fixed band requires band_doubling to be disabled");
+        }
+        if !self.band_doubling && self.band_doubling_start.is_some() {
+            return Err("Ksw2 band_doubling_start requires band_doubling to be enabled");
+        }
+        self.make_aligner(0, cm.sub, cm.open, cm.extend, trace)
+    }
+
+    fn build_with_costs(
+        &self,
+        costs: Costs,
+        trace: bool,
+        max_len: usize,
+    ) -> Result<Self::Aligner, &'static str> {
+        // Different extend costs for insertions and deletions are supported
+        // by aligning with equivalent symmetric costs.
+        let costs = costs
+            .to_symmetric()
+            .ok_or("Ksw2 does not support different insertion and deletion open costs")?;
+        let mut aligner = AlignerParamsTrait::build(self, costs.cm, trace, max_len)?;
+        aligner.costs = costs;
+        Ok(aligner)
+    }
+
+    fn build_extension(
+        &self,
+        costs: Costs,
+        trace: bool,
+        _max_len: usize,
+    ) -> Result<Box<dyn ExtensionAlignerTrait>, &'static str> {
+        if self.extension.is_none() {
+            return Err("Ksw2 extension alignment requires extension parameters");
+        }
+        if !matches!(
+            self.method,
+            Ksw2Method::ExtensionGreen | Ksw2Method::ExtensionSuzukiSse
+        ) {
+            return Err("Ksw2 extension alignment requires an Extension method");
+        }
+        if !costs.is_symmetric() {
+            return Err("Ksw2 does not support different insertion and deletion costs");
+        }
+        if self.band_doubling && self.band.is_some() {
+            return Err("Ksw2 fixed band requires band_doubling to be disabled");
+        }
+        if !self.band_doubling && self.band_doubling_start.is_some() {
+            return Err("Ksw2 band_doubling_start requires band_doubling to be enabled");
+        }
+        // Extension alignment is not global, so the match bonus must be used as-is.
+        let Some(r#match) = costs.match_bonus else {
+            return Err("Ksw2 extension alignment requires a match bonus");
+        };
+        let (open, extend) = costs.ins();
+        let aligner = self.make_aligner(r#match, costs.sub, open, extend, trace)?;
+        Ok(Box::new(aligner))
+    }
 
     fn is_exact(&self) -> bool {
-        true
+        self.band.is_none() && self.extension.is_none()
+    }
+
+    fn is_extension(&self) -> bool {
+        self.extension.is_some()
+    }
+
+    fn info(&self) -> AlignerInfo {
+        let simd = match self.method {
+            Ksw2Method::GlobalSuzukiSse
+            | Ksw2Method::ExtensionSuzukiSse
+            | Ksw2Method::DualAffineExtensionSuzukiSse => Some("sse"),
+            _ => None,
+        };
+        aligner_info! {
+            name: "ksw2",
+            crate: "ksw2-sys",
+            env: "KSW2_SYS",
+            simd: simd,
+            citation: "Li, H. (2018). Minimap2: pairwise alignment for nucleotide sequences. Bioinformatics, 34(18), 3094–3100. https://doi.org/10.1093/bioinformatics/bty191",
+        }
     }
 }
 
 impl AlignerTrait for Ksw2 {
     fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>, AlignerStats) {
-        let a_mapped: Vec<u8> = a.iter().map(|x| self.encoding[*x as usize]).collect();
-        let b_mapped: Vec<u8> = b.iter().map(|x| self.encoding[*x as usize]).collect();
+        self.encode(a, b);
         unsafe {
             let score;
             // Returned length of cigar. Out-only.
             let mut n_cigar: i32 = 0;
-            // Allocated cigar. Must be free afterwards.
-            let mut ksw2_cigar: *mut u32 = std::ptr::null_mut();
 
             // Documentation is at
             // https://github.com/lh3/ksw2/blob/06b2183b0f6646d82f2e3f5884008a1b4582f5b5/ksw2.h#L44.
@@ -100,14 +242,6 @@ impl AlignerTrait for Ksw2 {
                 Ksw2Method::GlobalGreen
                 | Ksw2Method::GlobalSuzuki
                 | Ksw2Method::GlobalSuzukiSse => {
-                    // Length of allocated cigar memory. In-out.
-                    let mut m_cigar: i32 = 0;
-
-                    if self.trace {
-                        ksw2_cigar = libc::malloc(16) as *mut u32;
-                        m_cigar = 4;
-                    }
-
                     let function = match (self.params.band_doubling, self.params.method) {
                         (false, Ksw2Method::GlobalGreen) => ksw_gg,
                         (false, Ksw2Method::GlobalSuzuki) => ksw_gg2,
@@ -122,25 +256,27 @@ impl AlignerTrait for Ksw2 {
                         // don't use a kmalloc memory pool.
                         std::ptr::null_mut(),
                         // Input sequences
-                        a_mapped.len() as i32,
-                        a_mapped.as_ptr(),
-                        b_mapped.len() as i32,
-                        b_mapped.as_ptr(),
+                        self.a_mapped.len() as i32,
+                        self.a_mapped.as_ptr(),
+                        self.b_mapped.len() as i32,
+                        self.b_mapped.as_ptr(),
                         M as i8,
                         // Scoring matrix and gap penalties
                         self.score_matrix.as_ptr(),
                         self.open,
                         self.extend,
-                        // band: disabled
-                        -1,
-                        (&mut m_cigar) as *mut i32,
+                        self.band(),
+                        // The reused cigar buffer and its allocated length. In-out.
+                        (&mut self.m_cigar) as *mut i32,
                         (&mut n_cigar) as *mut i32,
-                        (&mut ksw2_cigar) as *mut *mut u32,
+                        (&mut self.cigar) as *mut *mut u32,
                     );
                 }
 
                 Ksw2Method::ExtensionGreen => {
                     let mut output: ksw_extz_t = std::mem::zeroed();
+                    output.cigar = self.cigar;
+                    output.m_cigar = self.m_cigar;
                     let function = if self.params.band_doubling {
                         ksw_extz_band_doubling
                     } else {
@@ -150,17 +286,16 @@ impl AlignerTrait for Ksw2 {
                         // don't use a kmalloc memory pool.
                         std::ptr::null_mut(),
                         // Input sequences
-                        a_mapped.len() as i32,
-                        a_mapped.as_ptr(),
-                        b_mapped.len() as i32,
-                        b_mapped.as_ptr(),
+                        self.a_mapped.len() as i32,
+                        self.a_mapped.as_ptr(),
+                        self.b_mapped.len() as i32,
+                        self.b_mapped.as_ptr(),
                         M as i8,
                         // Scoring matrix and gap penalties
                         self.score_matrix.as_ptr(),
                         self.open,
                         self.extend,
-                        // band: disabled
-                        -1,
+                        self.band(),
                         // zdrop: disabled
                         -1,
                         // TODO(ragnar): Figure out what this means.
@@ -174,10 +309,13 @@ impl AlignerTrait for Ksw2 {
                     );
                     score = output.score;
                     n_cigar = output.n_cigar;
-                    ksw2_cigar = output.cigar;
+                    self.cigar = output.cigar;
+                    self.m_cigar = output.m_cigar;
                 }
                 Ksw2Method::ExtensionSuzukiSse => {
                     let mut output: ksw_extz_t = std::mem::zeroed();
+                    output.cigar = self.cigar;
+                    output.m_cigar = self.m_cigar;
                     let function = if self.params.band_doubling {
                         ksw_extz2_sse_band_doubling
                     } else {
@@ -187,17 +325,16 @@ impl AlignerTrait for Ksw2 {
                         // don't use a kmalloc memory pool.
                         std::ptr::null_mut(),
                         // Input sequences
-                        a_mapped.len() as i32,
-                        a_mapped.as_ptr(),
-                        b_mapped.len() as i32,
-                        b_mapped.as_ptr(),
+                        self.a_mapped.len() as i32,
+                        self.a_mapped.as_ptr(),
+                        self.b_mapped.len() as i32,
+                        self.b_mapped.as_ptr(),
                         M as i8,
                         // Scoring matrix and gap penalties
                         self.score_matrix.as_ptr(),
                         self.open,
                         self.extend,
-                        // band: disabled
-                        -1,
+                        self.band(),
                         // zdrop: disabled
                         -1,
                         // TODO(ragnar): Figure out what this means.
@@ -212,36 +349,168 @@ impl AlignerTrait for Ksw2 {
                     );
                     score = output.score;
                     n_cigar = output.n_cigar;
-                    ksw2_cigar = output.cigar;
+                    self.cigar = output.cigar;
+                    self.m_cigar = output.m_cigar;
                 }
                 Ksw2Method::DualAffineExtensionGreen => todo!(),
                 Ksw2Method::DualAffineExtensionSuzukiSse => todo!(),
             };
+            let cigar = self
+                .trace
+                .then(|| convert_cigar(self.cigar, n_cigar, &self.a_mapped, &self.b_mapped));
+            let cost = self.costs.cost(-score, a.len(), b.len());
+            (cost, cigar, AlignerStats::default())
+        }
+    }
+}
+
+impl ExtensionAlignerTrait for Ksw2 {
+    fn align_extension(&mut self, a: Seq, b: Seq) -> (Extension, Option<Cigar>, AlignerStats) {
+        self.encode(a, b);
+        let extension = self.params.extension.unwrap();
+        // flag: KSW_EZ_EXTZ_ONLY=0x40, and KSW_EZ_SCORE_ONLY=0x01 without trace.
+        // https://github.com/lh3/ksw2/blob/master/ksw2.h#L8
+        let flag = 0x40 | if self.trace { 0 } else { 1 };
+        unsafe {
+            let mut output: ksw_extz_t = std::mem::zeroed();
+            output.cigar = self.cigar;
+            output.m_cigar = self.m_cigar;
+            match self.params.method {
+                Ksw2Method::ExtensionGreen => {
+                    let function = if self.params.band_doubling {
+                        ksw_extz_band_doubling
+                    } else {
+                        ksw_extz
+                    };
+                    function(
+                        // don't use a kmalloc memory pool.
+                        std::ptr::null_mut(),
+                        // Input sequences
+                        self.a_mapped.len() as i32,
+                        self.a_mapped.as_ptr(),
+                        self.b_mapped.len() as i32,
+                        self.b_mapped.as_ptr(),
+                        M as i8,
+                        // Scoring matrix and gap penalties
+                        self.score_matrix.as_ptr(),
+                        self.open,
+                        self.extend,
+                        self.band(),
+                        extension.zdrop,
+                        flag,
+                        &mut output,
+                    );
+                }
+                Ksw2Method::ExtensionSuzukiSse => {
+                    let function = if self.params.band_doubling {
+                        ksw_extz2_sse_band_doubling
+                    } else {
+                        ksw_extz2_sse
+                    };
+                    function(
+                        // don't use a kmalloc memory pool.
+                        std::ptr::null_mut(),
+                        // Input sequences
+                        self.a_mapped.len() as i32,
+                        self.a_mapped.as_ptr(),
+                        self.b_mapped.len() as i32,
+                        self.b_mapped.as_ptr(),
+                        M as i8,
+                        // Scoring matrix and gap penalties
+                        self.score_matrix.as_ptr(),
+                        self.open,
+                        self.extend,
+                        self.band(),
+                        extension.zdrop,
+                        extension.end_bonus,
+                        flag,
+                        &mut output,
+                    );
+                }
+                _ => unreachable!("Ksw2 extension alignment requires an Extension method"),
+            }
+
+            // When the end of `a` is reached with a better score (including end bonus),
+            // ksw2 traces back from there instead of from the maximum.
+            // It only sets `reach_end` when tracing, so this is computed here
+            // in the same way for both modes.
+            let end_bonus = match self.params.method {
+                Ksw2Method::ExtensionSuzukiSse => extension.end_bonus,
+                _ => 0,
+            };
+            let reach_end = output.zdropped() == 0 && output.mqe + end_bonus > output.max() as i32;
+            let result = if reach_end {
+                Extension {
+                    score: output.mqe,
+                    a_end: a.len(),
+                    b_end: (output.mqe_t + 1) as usize,
+                }
+            } else {
+                Extension {
+                    score: output.max() as Cost,
+                    a_end: (output.max_q + 1) as usize,
+                    b_end: (output.max_t + 1) as usize,
+                }
+            };
+            self.cigar = output.cigar;
+            self.m_cigar = output.m_cigar;
             let cigar = self.trace.then(|| {
-                let cigar = std::slice::from_raw_parts_mut(ksw2_cigar, n_cigar as usize);
-                let cigar = Cigar::resolve_matches(
-                    cigar.into_iter().map(|&mut val| {
-                        CigarElem {
-                            op: match val & 15 {
-                                // NOTE: This Match will be resolved to Match or Sub as needed.
-                                0 => CigarOp::Match,
-                                1 => CigarOp::Del,
-                                2 => CigarOp::Ins,
-                                7 => CigarOp::Match,
-                                8 => CigarOp::Sub,
-                                _ => panic!(),
-                            },
-                            cnt: val as I / 16,
-                        }
-                    }),
-                    &a_mapped,
-                    &b_mapped,
-                );
-                libc::free(ksw2_cigar as *mut c_void);
-                cigar
+                convert_cigar(
+                    self.cigar,
+                    output.n_cigar,
+                    &self.a_mapped[..result.a_end],
+                    &self.b_mapped[..result.b_end],
+                )
             });
-            let cost = -score;
-            (cost, cigar, AlignerStats::default())
+            let mut stats = AlignerStats::default();
+            stats.insert("zdropped".into(), output.zdropped() as f64);
+            (result, cigar, stats)
         }
     }
 }
+
+impl Ksw2 {
+    /// Encode `a` and `b` into the reused `a_mapped` and `b_mapped` buffers.
+    fn encode(&mut self, a: Seq, b: Seq) {
+        self.a_mapped.clear();
+        self.a_mapped
+            .extend(a.iter().map(|x| self.encoding[*x as usize]));
+        self.b_mapped.clear();
+        self.b_mapped
+            .extend(b.iter().map(|x| self.encoding[*x as usize]));
+    }
+
+    /// The band width to pass to ksw2: the fixed band, or the initial band for band doubling.
+    /// -1 disables the band.
+    fn band(&self) -> i32 {
+        let band = if self.params.band_doubling {
+            self.params.band_doubling_start
+        } else {
+            self.params.band
+        };
+        band.map_or(-1, |w| w as i32)
+    }
+}
+
+/// Convert a cigar returned by ksw2 to a `Cigar`, resolving matches and substitutions.
+unsafe fn convert_cigar(ksw2_cigar: *mut u32, n_cigar: i32, a: Seq, b: Seq) -> Cigar {
+    let cigar = std::slice::from_raw_parts(ksw2_cigar, n_cigar as usize);
+    Cigar::resolve_matches(
+        cigar.iter().map(|&val| {
+            CigarElem {
+                op: match val & 15 {
+                    // NOTE: This Match will be resolved to Match or Sub as needed.
+                    0 => CigarOp::Match,
+                    1 => CigarOp::Del,
+                    2 => CigarOp::Ins,
+                    7 => CigarOp::Match,
+                    8 => CigarOp::Sub,
+                    _ => panic!(),
+                },
+                cnt: val as I / 16,
+            }
+        }),
+        a,
+        b,
+    )
+}
@@ -1,17 +1,101 @@
-use super::super::*;
-use parasailors::{global_alignment_score, Matrix, Profile};
+use crate::*;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int};
+
+// The library itself is built and linked by `parasailors`.
+use parasailors as _;
+
+/// The parts of the parasail C API used here, including the functions for
+/// fixed score widths, which `parasailors` does not expose.
+mod ffi {
+    use super::*;
+
+    #[repr(C)]
+    pub struct ParasailMatrix {
+        _private: [u8; 0],
+    }
+    #[repr(C)]
+    pub struct ParasailProfile {
+        _private: [u8; 0],
+    }
+    #[repr(C)]
+    pub struct ParasailResult {
+        _private: [u8; 0],
+    }
+
+    extern "C" {
+        pub fn parasail_matrix_create(
+            alphabet: *const c_char,
+            r#match: c_int,
+            mismatch: c_int,
+        ) -> *mut ParasailMatrix;
+        pub fn parasail_matrix_free(matrix: *mut ParasailMatrix);
+
+        pub fn parasail_profile_create_8(
+            s1: *const c_char,
+            s1_len: c_int,
+            matrix: *const ParasailMatrix,
+        ) -> *mut ParasailProfile;
+        pub fn parasail_profile_create_16(
+            s1: *const c_char,
+            s1_len: c_int,
+            matrix: *const ParasailMatrix,
+        ) -> *mut ParasailProfile;
+        pub fn parasail_profile_create_32(
+            s1: *const c_char,
+            s1_len: c_int,
+            matrix: *const ParasailMatrix,
+        ) -> *mut ParasailProfile;
+        pub fn parasail_profile_free(profile: *mut ParasailProfile);
+
+        pub fn parasail_nw_striped_profile_8(
+            profile: *const ParasailProfile,
+            s2: *const c_char,
+            s2_len: c_int,
+            open: c_int,
+            gap: c_int,
+        ) -> *mut ParasailResult;
+        pub fn parasail_nw_striped_profile_16(
+            profile: *const ParasailProfile,
+            s2: *const c_char,
+            s2_len: c_int,
+            open: c_int,
+            gap: c_int,
+        ) -> *mut ParasailResult;
+        pub fn parasail_nw_striped_profile_32(
+            profile: *const ParasailProfile,
+            s2: *const c_char,
+            s2_len: c_int,
+            open: c_int,
+            gap: c_int,
+        ) -> *mut ParasailResult;
+
+        pub fn parasail_result_get_score(result: *const ParasailResult) -> c_int;
+        pub fn parasail_result_is_saturated(result: *const ParasailResult) -> c_int;
+        pub fn parasail_result_free(result: *mut ParasailResult);
+    }
+}
+use ffi::*;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct ParasailStripedParams;
 
 pub struct ParasailStriped {
-    matrix: Matrix,
+    matrix: *mut ParasailMatrix,
     gap_open: i32,
     gap_extend: i32,
     s: ScoreModel,
+    /// The costs of `s`, which may be equivalent to asymmetric costs.
+    costs: SymmetricCosts,
 }
 
-impl AlignerParams for ParasailStripedParams {
+impl Drop for ParasailStriped {
+    fn drop(&mut self) {
+        unsafe { parasail_matrix_free(self.matrix) };
+    }
+}
+
+impl AlignerParamsTrait for ParasailStripedParams {
     type Aligner = ParasailStriped;
 
     fn build(
@@ -24,31 +108,125 @@ impl AlignerParams for ParasailStripedParams {
             return Err("Parasail does not support returning a trace");
         }
         let s = ScoreModel::from_costs(cm);
+        let alphabet = CString::new("ACGT").unwrap();
         Ok(Self::Aligner {
-            matrix: Matrix::create("ACGT", s.r#match as _, s.sub as _),
+            matrix: unsafe {
+                parasail_matrix_create(alphabet.as_ptr(), s.r#match as _, s.sub as _)
+            },
             gap_open: -s.open - s.extend,
             gap_extend: -s.extend,
             s,
+            costs: cm.into(),
         })
     }
 
+    fn build_with_costs(
+        &self,
+        costs: Costs,
+        trace: bool,
+        max_len: usize,
+    ) -> Result<Self::Aligner, &'static str> {
+        // Different extend costs for insertions and deletions are supported
+        // by aligning with equivalent symmetric costs.
+        let costs = costs
+            .to_symmetric()
+            .ok_or("Parasail does not support different insertion and deletion open costs")?;
+        let mut aligner = AlignerParamsTrait::build(self, costs.cm, trace, max_len)?;
+        aligner.costs = costs;
+        Ok(aligner)
+    }
+
     fn is_exact(&self) -> bool {
-        // FIXME: Turn this back to true after fixing overflows.
-        false
+        // Saturated scores are recomputed with wider scores. 32 bit scores only
+        // saturate when the cost does not fit in a `Cost` either.
+        true
+    }
+
+    fn info(&self) -> AlignerInfo {
+        aligner_info! {
+            name: "Parasail",
+            crate: "parasailors",
+            env: "PARASAILORS",
+            simd: runtime_simd(),
+            citation: "Daily, J. (2016). Parasail: SIMD C library for global, semi-global, and local pairwise sequence alignments. BMC Bioinformatics, 17, 81. https://doi.org/10.1186/s12859-016-0930-z",
+        }
+    }
+}
+
+impl ParasailStriped {
+    /// The score of aligning `a` and `b` with `bits`-wide scores,
+    /// or `None` when the scores saturated.
+    fn score(&self, a: Seq, b: Seq, bits: u32) -> Option<i32> {
+        let (a_ptr, a_len) = (a.as_ptr() as *const c_char, a.len() as c_int);
+        let (b_ptr, b_len) = (b.as_ptr() as *const c_char, b.len() as c_int);
+        let (open, extend) = (self.gap_open, self.gap_extend);
+        unsafe {
+            let (profile, result) = match bits {
+                8 => {
+                    let profile = parasail_profile_create_8(a_ptr, a_len, self.matrix);
+                    let result = parasail_nw_striped_profile_8(profile, b_ptr, b_len, open, extend);
+                    (profile, result)
+                }
+                16 => {
+                    let profile = parasail_profile_create_16(a_ptr, a_len, self.matrix);
+                    let result =
+                        parasail_nw_striped_profile_16(profile, b_ptr, b_len, open, extend);
+                    (profile, result)
+                }
+                32 => {
+                    let profile = parasail_profile_create_32(a_ptr, a_len, self.matrix);
+                    let result =
+                        parasail_nw_striped_profile_32(profile, b_ptr, b_len, open, extend);
+                    (profile, result)
+                }
+                _ => unreachable!(),
+            };
+            // Parasail flags results whose scores hit the bounds of their type during the DP.
+            let saturated = parasail_result_is_saturated(result) != 0;
+            let score = parasail_result_get_score(result);
+            parasail_result_free(result);
+            parasail_profile_free(profile);
+            (!saturated).then_some(score)
+        }
+    }
+}
+
+impl AlignerTrait for ParasailStriped {
+    fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>, AlignerStats) {
+        let mut stats = AlignerStats::default();
+        // Start with 8 bit scores, and retry with wider scores when they saturate.
+        let mut retries = 0;
+        let score = [8, 16, 32].into_iter().find_map(|bits| {
+            let score = self.score(a, b, bits);
+            retries += score.is_none() as usize;
+            score
+        });
+        if retries > 0 {
+            stats.insert("overflow_retries".into(), retries as f64);
+        }
+        let Some(score) = score else {
+            stats.insert("saturated".into(), 1.);
+            return (Cost::MAX, None, stats);
+        };
+        let cost = self.s.global_cost(score, a.len(), b.len());
+        (self.costs.cost(cost, a.len(), b.len()), None, stats)
     }
 }
 
-impl Aligner for ParasailStriped {
-    fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>) {
-        let a_len = a.len();
-        let a = Profile::new(a, &self.matrix);
-        (
-            self.s.global_cost(
-                global_alignment_score(&a, b, self.gap_open, self.gap_extend),
-                a_len,
-                b.len(),
-            ),
-            None,
-        )
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_saturated_scores() {
+        let mut aligner =
+            AlignerParamsTrait::build(&ParasailStripedParams, CostModel::unit(), false, 0).unwrap();
+        let (cost, _, stats) = aligner.align(b"ACGT", b"AGT");
+        assert_eq!(cost, 1);
+        assert!(!stats.contains_key("overflow_retries"));
+        // A score of -200 does not fit in 8 bits.
+        let (cost, _, stats) = aligner.align(&[b'A'; 200], &[b'C'; 200]);
+        assert_eq!(cost, 200);
+        assert_eq!(stats["overflow_retries"], 1.);
     }
 }
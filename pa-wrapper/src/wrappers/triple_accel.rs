@@ -2,11 +2,18 @@ use crate::*;
 use triple_accel::*;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
-pub struct TripleAccelParams;
+pub struct TripleAccelParams {
+    /// Only find alignments with cost at most `k`, using `levenshtein_simd_k`.
+    /// By default, the band is doubled until an alignment is found.
+    /// Alignments with larger cost are not found, so banded runs are not exact.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub k: Option<u32>,
+}
 
 pub struct TripleAccel {
     costs: ::triple_accel::levenshtein::EditCosts,
     trace: bool,
+    k: Option<u32>,
 }
 
 impl AlignerParamsTrait for TripleAccelParams {
@@ -27,18 +34,45 @@ impl AlignerParamsTrait for TripleAccelParams {
             cm.open as _,
             None,
         );
-        Ok(Self::Aligner { costs, trace })
+        Ok(Self::Aligner {
+            costs,
+            trace,
+            k: self.k,
+        })
     }
 
     fn is_exact(&self) -> bool {
-        true
+        self.k.is_none()
+    }
+
+    fn info(&self) -> AlignerInfo {
+        aligner_info! {
+            name: "triple_accel",
+            crate: "triple_accel",
+            env: "TRIPLE_ACCEL",
+            simd: runtime_simd(),
+            citation: "Liu, D. triple_accel: Rust edit distance routines accelerated using SIMD. https://github.com/Daniel-Liu-c0deb0t/triple_accel",
+        }
     }
 }
 
 impl AlignerTrait for TripleAccel {
     fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>, AlignerStats) {
-        let (cost, edits) =
-            ::triple_accel::levenshtein::levenshtein_exp_with_opts(a, b, self.trace, self.costs);
+        let (cost, edits) = match self.k {
+            None => {
+                ::triple_accel::levenshtein::levenshtein_exp_with_opts(a, b, self.trace, self.costs)
+            }
+            Some(k) => match ::triple_accel::levenshtein::levenshtein_simd_k_with_opts(
+                a, b, k, self.trace, self.costs,
+            ) {
+                Some(result) => result,
+                None => {
+                    let mut stats = AlignerStats::default();
+                    stats.insert("out_of_band".into(), 1.);
+                    return (Cost::MAX, None, stats);
+                }
+            },
+        };
 
         let cigar = edits.map(|edits| Cigar {
             ops: edits
@@ -59,3 +93,20 @@ impl AlignerTrait for TripleAccel {
         (cost as _, cigar, AlignerStats::default())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Older results store the parameters of this former unit struct as `null`.
+    #[test]
+    fn deserialize_null_params() {
+        let params: AlignerParams = serde_json::from_str(r#"{"TripleAccel":null}"#).unwrap();
+        assert_eq!(params, AlignerParams::TripleAccel(Default::default()));
+        let params: AlignerParams = serde_json::from_str(r#"{"TripleAccel":{"k":10}}"#).unwrap();
+        assert_eq!(
+            params,
+            AlignerParams::TripleAccel(TripleAccelParams { k: Some(10) })
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::*;
+pub use rust_wfa2::aligner::Heuristic;
 use rust_wfa2::{
     aligner::{
         AlignmentScope, AlignmentStatus, MemoryModel, WFAligner, WFAlignerEdit, WFAlignerGapAffine,
@@ -17,10 +18,20 @@ fn default_heuristic() -> rust_wfa2::aligner::Heuristic {
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct WfaParams {
+    /// `MemoryUltraLow` runs the bidirectional BiWFA.
     #[serde(default = "default_memory_model")]
     pub memory_model: rust_wfa2::aligner::MemoryModel,
     #[serde(default = "default_heuristic")]
     pub heuristic: rust_wfa2::aligner::Heuristic,
+    /// Give up after this many alignment steps, i.e. when the score exceeds this.
+    #[serde(default)]
+    pub max_alignment_steps: Option<i32>,
+    /// Memory limits in bytes: compact the wavefronts above `max_memory_resident`,
+    /// and give up above `max_memory_abort`.
+    #[serde(default)]
+    pub max_memory_resident: Option<u64>,
+    #[serde(default)]
+    pub max_memory_abort: Option<u64>,
 }
 
 impl Default for WfaParams {
@@ -28,6 +39,9 @@ impl Default for WfaParams {
         Self {
             memory_model: rust_wfa2::aligner::MemoryModel::MemoryUltraLow,
             heuristic: rust_wfa2::aligner::Heuristic::None,
+            max_alignment_steps: None,
+            max_memory_resident: None,
+            max_memory_abort: None,
         }
     }
 }
@@ -63,14 +77,43 @@ impl AlignerParamsTrait for WfaParams {
             cm if cm.is_affine() => {
                 WFAlignerGapAffine::new(cm.sub, cm.open, cm.extend, scope, self.memory_model)
             }
-            _ => return Err("WFA does not support match bonus!"),
+            _ => return Err("WFA only supports unit, linear, and affine costs"),
         };
         aligner.set_heuristic(self.heuristic);
+        if let Some(max_alignment_steps) = self.max_alignment_steps {
+            aligner.set_max_alignment_steps(max_alignment_steps);
+        }
+        if self.max_memory_resident.is_some() || self.max_memory_abort.is_some() {
+            aligner.set_max_memory(
+                self.max_memory_resident.unwrap_or(u64::MAX),
+                self.max_memory_abort.unwrap_or(u64::MAX),
+            );
+        }
         Ok(Self::Aligner { cm, aligner })
     }
 
     fn is_exact(&self) -> bool {
+        // Runs that reach the step or memory abort limit return `Cost::MAX`.
+        // The resident memory limit only compacts the wavefronts.
         self.heuristic == aligner::Heuristic::None
+            && self.max_alignment_steps.is_none()
+            && self.max_memory_abort.is_none()
+    }
+
+    fn info(&self) -> AlignerInfo {
+        // The ultra-low memory mode runs the bidirectional BiWFA.
+        let (name, citation) = if self.memory_model == MemoryModel::MemoryUltraLow {
+            ("BiWFA", "Marco-Sola, S., Eizenga, J. M., Guarracino, A., Paten, B., Garrison, E., & Moreto, M. (2023). Optimal gap-affine alignment in O(s) space. Bioinformatics, 39(2). https://doi.org/10.1093/bioinformatics/btad074")
+        } else {
+            ("WFA", "Marco-Sola, S., Moure, J. C., Moreto, M., & Espinosa, A. (2021). Fast gap-affine pairwise alignment using the wavefront algorithm. Bioinformatics, 37(4), 456–463. https://doi.org/10.1093/bioinformatics/btaa777")
+        };
+        aligner_info! {
+            name: name,
+            crate: "rust-wfa2",
+            env: "RUST_WFA2",
+            simd: None,
+            citation: citation,
+        }
     }
 }
 
@@ -78,7 +121,10 @@ impl AlignerTrait for Wfa {
     fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>, AlignerStats) {
         let status = self.aligner.align_end_to_end(a, b);
         if status != AlignmentStatus::StatusSuccessful {
-            return (Cost::MAX, None, AlignerStats::default());
+            // Count failures per status, e.g. when the step or memory limit was reached.
+            let mut stats = AlignerStats::default();
+            stats.insert(format!("status_{status:?}"), 1.);
+            return (Cost::MAX, None, stats);
         }
         let cost = self.aligner.score();
         let cigar = self.aligner.cigar();